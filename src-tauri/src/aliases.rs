@@ -0,0 +1,362 @@
+// 别名表：内置常用软件的简称与俗称，加上已安装应用的名称与本地化显示名，再叠加用户在 aliases.json 中的定义
+//
+// 一个别名可以有多个目标 (如 db -> 豆包、豆瓣)，按顺序全部参与搜索，靠前目标的结果排得更靠前。
+// 内置与应用名中同一别名的多条定义按出现顺序合为一组目标。
+//
+// 优先级：用户定义 > 内置 > 应用名；高优先级来源的目标整组取代低优先级的，用户文件中后写的覆盖先写的。
+// 被覆盖的定义不会悄悄消失，而是记入冲突报告 (目标都已包含在生效定义中的不算冲突)。
+//
+// aliases.json 放在配置目录，可以手工编辑，保存后自动重新加载：
+//   {
+//     "aliases": { "jy": "videofusion", "db": ["douban", "doubao"] },
+//     "removed": ["me"]
+//   }
+// 目标可以是单个字符串或按优先顺序排列的数组；removed 中的别名不再使用内置或应用名提供的定义。
+
+use serde::de::{MapAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::apps::InstalledApp;
+use crate::{chinese, matcher};
+
+/// 内置别名：覆盖设计、社交、工具、办公等常用软件，支持拼音缩写、中文俗称、英文原名
+pub const BUILTIN_ALIASES: &[(&str, &str)] = &[
+    // 设计类
+    ("ps", "photoshop"), ("lr", "lightroom"), ("pr", "premiere"), ("ae", "after effects"), ("ai", "illustrator"),
+    ("id", "indesign"), ("au", "audition"), ("dw", "dreamweaver"), ("an", "animate"), ("pl", "prelude"),
+    ("br", "bridge"), ("ch", "character animator"), ("me", "media encoder"), ("ic", "incopy"),
+    ("sc", "scout"), ("xd", "xd"), ("dc", "acrobat"), ("dpp", "digital photo professional"),
+    ("fcpx", "final cut pro"), ("c4d", "cinema 4d"), ("sketch", "sketch"), ("figma", "figma"),
+    ("photoshop", "photoshop"), ("illustrator", "illustrator"), ("premiere", "premiere"),
+    ("aftereffects", "after effects"), ("lightroom", "lightroom"),
+    // 社交/办公
+    ("wx", "wechat"), ("微信", "wechat"), ("qq", "qq"), ("dd", "dingtalk"), ("钉钉", "dingtalk"),
+    ("fs", "feishu"), ("飞书", "feishu"), ("lark", "feishu"), ("word", "microsoft word"), ("excel", "microsoft excel"),
+    ("ppt", "microsoft powerpoint"), ("wps", "wpsoffice"), ("pdf", "acrobat"), ("obs", "obs studio"),
+    ("yx", "neteasemail"), ("邮箱", "mail"), ("notes", "notes"), ("memo", "notes"), ("wechat", "wechat"),
+    ("dingtalk", "dingtalk"), ("feishu", "feishu"),
+    // 视频/娱乐/AI
+    ("jy", "videofusion"), ("剪映", "videofusion"), ("capcut", "videofusion"), ("vf", "videofusion"),
+    ("db", "doubao"), ("豆包", "doubao"), ("doubao", "doubao"), ("videofusion", "videofusion"),
+    ("db", "douban"), ("dy", "douyin"), ("bili", "bilibili"), ("bz", "bilibili"), ("music", "music"),
+    ("网易云", "neteasemusic"), ("spotify", "spotify"), ("douyin", "douyin"), ("tiktok", "douyin"),
+    ("jianying", "videofusion"), ("jianyingpro", "videofusion"),
+    // 生产力
+    ("wp", "wpsoffice"), ("pages", "pages"), ("numbers", "numbers"), ("keynote", "keynote"),
+    // 工具/开发
+    ("llq", "browser"), ("浏览器", "browser"), ("safari", "safari"), ("chrome", "google chrome"),
+    ("edge", "microsoft edge"), ("fd", "finder"), ("访达", "finder"), ("zd", "terminal"), ("终端", "terminal"),
+    ("iterm", "iterm"), ("code", "visual studio code"), ("vs", "visual studio code"), ("vscode", "visual studio code"),
+    ("st", "sublime text"), ("idea", "intellij idea"), ("webstorm", "webstorm"), ("py", "pycharm"),
+    ("git", "github"), ("postman", "postman"), ("docker", "docker"),
+    // 系统/其他
+    ("sz", "settings"), ("设置", "settings"), ("jh", "calculator"), ("计算器", "calculator"),
+    ("activity", "activity monitor"), ("monitor", "activity monitor"), ("disk", "disk utility"),
+    ("keychain", "keychain access"), ("console", "console"),
+];
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum AliasSource {
+    App,
+    Builtin,
+    User,
+}
+
+#[derive(Serialize, Clone, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AliasEntry {
+    pub alias: String,
+    /// 按优先顺序排列的目标
+    pub targets: Vec<String>,
+    pub source: AliasSource,
+}
+
+/// 同一个别名 (折叠后) 有多个不同目标时，实际使用的与被忽略的定义
+#[derive(Serialize, Clone, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AliasConflict {
+    pub key: String,
+    pub used: AliasEntry,
+    pub ignored: Vec<AliasEntry>,
+}
+
+/// 别名定义按出现顺序保存，手工编辑时写重复的键也能报告出来
+#[derive(Default, Debug, PartialEq)]
+struct Entries(Vec<(String, Vec<String>)>);
+
+/// 配置文件中的目标：单个字符串或数组
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Targets {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl<'de> Deserialize<'de> for Entries {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct EntriesVisitor;
+        impl<'de> Visitor<'de> for EntriesVisitor {
+            type Value = Entries;
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("别名 -> 目标 (字符串或数组) 的映射")
+            }
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Entries, A::Error> {
+                let mut entries = Vec::new();
+                while let Some((alias, targets)) = map.next_entry::<String, Targets>()? {
+                    let targets = match targets {
+                        Targets::One(target) => vec![target],
+                        Targets::Many(targets) => targets,
+                    };
+                    entries.push((alias, targets));
+                }
+                Ok(Entries(entries))
+            }
+        }
+        deserializer.deserialize_map(EntriesVisitor)
+    }
+}
+
+impl Serialize for Entries {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        // 只有一个目标时写成字符串，与手写的习惯一致
+        serializer.collect_map(self.0.iter().map(|(alias, targets)| match targets.as_slice() {
+            [target] => (alias, serde_json::Value::from(target.as_str())),
+            _ => (alias, serde_json::Value::from(targets.clone())),
+        }))
+    }
+}
+
+/// aliases.json 的内容
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+#[serde(default)]
+pub struct UserAliases {
+    aliases: Entries,
+    removed: Vec<String>,
+}
+
+impl UserAliases {
+    pub fn parse(content: &str) -> Result<Self, String> {
+        serde_json::from_str(content).map_err(|e| format!("别名配置格式错误: {}", e))
+    }
+
+    /// 读取配置文件；文件不存在时为空
+    pub fn load(path: &Path) -> Result<Self, String> {
+        match std::fs::read_to_string(path) {
+            Ok(content) => Self::parse(&content),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(format!("读取别名配置失败: {}", e)),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| format!("无法创建配置目录: {}", e))?;
+        }
+        let content = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(path, content).map_err(|e| format!("保存别名配置失败: {}", e))
+    }
+
+    /// 添加或修改别名，`targets` 按优先顺序排列；之前移除过的同名别名恢复使用
+    pub fn set(&mut self, alias: &str, targets: &[String]) {
+        let key = matcher::fold(alias);
+        self.aliases.0.retain(|(a, _)| matcher::fold(a) != key);
+        self.aliases.0.push((alias.to_string(), targets.to_vec()));
+        self.removed.retain(|a| matcher::fold(a) != key);
+    }
+
+    /// 添加别名，已有同名定义时保留原有的
+    pub fn add_missing(&mut self, alias: &str, target: &str) {
+        let key = matcher::fold(alias);
+        if !self.aliases.0.iter().any(|(a, _)| matcher::fold(a) == key) {
+            self.set(alias, &[target.to_string()]);
+        }
+    }
+
+    /// 移除别名：删除用户定义，并屏蔽内置或应用名提供的同名定义
+    pub fn remove(&mut self, alias: &str) {
+        let key = matcher::fold(alias);
+        self.aliases.0.retain(|(a, _)| matcher::fold(a) != key);
+        if !self.removed.iter().any(|a| matcher::fold(a) == key) {
+            self.removed.push(alias.to_string());
+        }
+    }
+}
+
+/// 合并后的别名表
+#[derive(Default, Debug)]
+pub struct AliasTable {
+    /// 折叠后的别名 (含繁体别名的简体形式) -> 按优先顺序排列的目标
+    pub mapping: HashMap<String, Vec<String>>,
+    /// 实际生效的定义，按别名排序
+    pub entries: Vec<AliasEntry>,
+    pub conflicts: Vec<AliasConflict>,
+}
+
+impl AliasTable {
+    /// 按优先级合并三个来源；`apps` 为从已安装应用发现的 (别名, 目标)
+    pub fn build(builtin: &[(&str, &str)], apps: &[(String, String)], user: &UserAliases) -> Self {
+        let candidates = apps
+            .iter()
+            .map(|(a, t)| (a.as_str(), vec![t.clone()], AliasSource::App))
+            .chain(builtin.iter().map(|&(a, t)| (a, vec![t.to_string()], AliasSource::Builtin)))
+            .chain(user.aliases.0.iter().map(|(a, t)| (a.as_str(), t.clone(), AliasSource::User)));
+
+        let removed: Vec<String> = user.removed.iter().map(|a| matcher::fold(a)).collect();
+        // 折叠后的别名 -> 各来源的定义，按优先级从低到高
+        let mut by_key: HashMap<String, Vec<AliasEntry>> = HashMap::new();
+        for (alias, targets, source) in candidates {
+            let key = matcher::fold(alias);
+            if key.is_empty() || (source != AliasSource::User && removed.contains(&key)) {
+                continue;
+            }
+            let mut folded: Vec<String> = Vec::new();
+            for target in targets.iter().map(|t| matcher::fold(t)) {
+                if !target.is_empty() && !folded.contains(&target) {
+                    folded.push(target);
+                }
+            }
+            if folded.is_empty() {
+                continue;
+            }
+            let defs = by_key.entry(key).or_default();
+            match defs.last_mut() {
+                // 内置与应用名中的重复定义是同一别名的备选目标，依次追加
+                Some(last) if last.source == source && source != AliasSource::User => {
+                    for target in folded {
+                        if !last.targets.contains(&target) {
+                            last.targets.push(target);
+                        }
+                    }
+                }
+                _ => defs.push(AliasEntry { alias: alias.to_string(), targets: folded, source }),
+            }
+        }
+
+        let mut table = Self::default();
+        for (key, mut defs) in by_key {
+            let used = defs.pop().unwrap();
+            let ignored: Vec<AliasEntry> =
+                defs.into_iter().rev().filter(|d| d.targets.iter().any(|t| !used.targets.contains(t))).collect();
+            if !ignored.is_empty() {
+                table.conflicts.push(AliasConflict { key: key.clone(), used: used.clone(), ignored });
+            }
+            table.mapping.insert(key, used.targets.clone());
+            table.entries.push(used);
+        }
+        // 同时登记简体形式，简繁等价模式下繁体别名 (如 飛書) 也能命中；不覆盖已有的别名
+        let simplified: Vec<(String, Vec<String>)> = table
+            .mapping
+            .iter()
+            .map(|(k, t)| (chinese::to_simplified(k).into_owned(), t.clone()))
+            .filter(|(k, _)| !table.mapping.contains_key(k))
+            .collect();
+        for (key, target) in simplified {
+            table.mapping.entry(key).or_insert(target);
+        }
+        table.entries.sort_by(|a, b| a.alias.cmp(&b.alias));
+        table.conflicts.sort_by(|a, b| a.key.cmp(&b.key));
+        table
+    }
+}
+
+// 多个单词的名字取首字母简写 (如 visual studio code -> vsc)
+fn initials(name: &str) -> Option<String> {
+    if !name.contains(' ') && !name.contains('-') {
+        return None;
+    }
+    let short: String = name.split([' ', '-']).filter(|s| !s.is_empty()).map(|s| s.chars().next().unwrap_or(' ')).collect();
+    (short.chars().count() > 1).then_some(short)
+}
+
+/// 已安装应用提供的别名，目标都是应用的文件名：文件名与显示名 (含本地化名称，如 剪映 -> videofusion)
+/// 及其首字母简写，以及应用声明的关键词。`apps` 应已按名字排序，同一别名的多个目标才有稳定的顺序
+pub fn app_aliases(apps: &[InstalledApp]) -> Vec<(String, String)> {
+    let mut aliases = Vec::new();
+    for app in apps {
+        let target = matcher::fold(&app.name);
+        for name in std::iter::once(&app.name).chain(&app.display_names) {
+            let name = matcher::fold(name);
+            if let Some(short) = initials(&name) {
+                aliases.push((short, target.clone()));
+            }
+            aliases.push((name, target.clone()));
+        }
+        aliases.extend(app.keywords.iter().map(|k| (matcher::fold(k), target.clone())));
+    }
+    aliases
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn user_aliases_override_and_conflicts_are_reported() {
+        let builtin = [("ps", "photoshop"), ("db", "doubao"), ("db", "douban"), ("wx", "wechat"), ("me", "media encoder"), ("jy", "videofusion")];
+        let apps = vec![("ps".to_string(), "ps remote".to_string()), ("wechat".to_string(), "wechat".to_string())];
+        let user = UserAliases::parse(
+            r#"{ "aliases": { "WX": "wecom", "飛書": "feishu", "wx": "wechat work", "jy": ["capcut", "VideoFusion"] }, "removed": ["me"] }"#,
+        )
+        .unwrap();
+        let table = AliasTable::build(&builtin, &apps, &user);
+
+        assert_eq!(table.mapping["ps"], ["photoshop"]);
+        // 内置的重复定义按出现顺序成为多个目标，不算冲突
+        assert_eq!(table.mapping["db"], ["doubao", "douban"]);
+        assert_eq!(table.mapping["wx"], ["wechat work"]);
+        assert_eq!(table.mapping["jy"], ["capcut", "videofusion"]);
+        assert!(!table.mapping.contains_key("me"));
+        // 繁体别名同时登记简体形式
+        assert_eq!(table.mapping["飞书"], ["feishu"]);
+
+        let conflict = |key: &str| table.conflicts.iter().find(|c| c.key == key).unwrap();
+        assert_eq!(conflict("ps").ignored, vec![AliasEntry { alias: "ps".into(), targets: vec!["ps remote".into()], source: AliasSource::App }]);
+        // 用户文件中写了两次的键按出现顺序报告
+        let wx = conflict("wx");
+        assert_eq!(wx.used.source, AliasSource::User);
+        assert_eq!(wx.ignored.iter().map(|e| e.targets[0].as_str()).collect::<Vec<_>>(), ["wecom", "wechat"]);
+        // 目标都已包含在用户定义中，只是调整了顺序
+        assert!(table.conflicts.iter().all(|c| c.key != "wechat" && c.key != "db" && c.key != "jy"));
+    }
+
+    #[test]
+    fn set_and_remove_edit_the_user_file() {
+        let mut user = UserAliases::default();
+        user.set("JY", &["VideoFusion".to_string()]);
+        user.remove("me");
+        user.set("me", &["media encoder".to_string()]);
+        user.remove("jy");
+        user.set("db", &["douban".to_string(), "doubao".to_string()]);
+        let json = serde_json::to_string(&user).unwrap();
+        assert_eq!(json, r#"{"aliases":{"me":"media encoder","db":["douban","doubao"]},"removed":["jy"]}"#);
+        assert_eq!(UserAliases::parse(&json).unwrap(), user);
+        assert!(UserAliases::parse(r#"{ "aliases": ["ps"] }"#).is_err());
+        assert!(UserAliases::parse(r#"{ "aliases": { "ps": 1 } }"#).is_err());
+    }
+
+    #[test]
+    fn app_names_and_localized_names_become_aliases() {
+        let apps = [
+            InstalledApp { name: "VideoFusion-macOS".into(), display_names: vec!["剪映专业版".into(), "剪映".into()], keywords: vec![] },
+            InstalledApp { name: "org.gnome.Nautilus".into(), display_names: vec!["Files".into(), "文件".into()], keywords: vec!["文件夹".into()] },
+            InstalledApp { name: "Visual Studio Code".into(), ..Default::default() },
+        ];
+        let table = AliasTable::build(&[], &app_aliases(&apps), &UserAliases::default());
+        assert_eq!(table.mapping["剪映"], ["videofusion-macos"]);
+        assert_eq!(table.mapping["vm"], ["videofusion-macos"]);
+        assert_eq!(table.mapping["文件夹"], ["org.gnome.nautilus"]);
+        assert_eq!(table.mapping["files"], ["org.gnome.nautilus"]);
+        assert_eq!(table.mapping["vsc"], ["visual studio code"]);
+        assert!(table.conflicts.is_empty());
+    }
+
+    #[test]
+    fn builtin_table_has_no_conflicts() {
+        let table = AliasTable::build(BUILTIN_ALIASES, &[], &UserAliases::default());
+        assert!(table.conflicts.is_empty(), "{:?}", table.conflicts);
+        assert_eq!(table.mapping["db"], ["doubao", "douban"]);
+    }
+}
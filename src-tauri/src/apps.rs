@@ -0,0 +1,198 @@
+// 已安装应用的发现：除文件名外，还读取本地化的显示名与关键词，供别名表使用
+//
+//   macOS  /Applications 下的 .app：Info.plist 中的 CFBundleDisplayName、CFBundleName，
+//          以及各 .lproj/InfoPlist.strings 中本地化的同名键 (如 VideoFusion.app 显示为 剪映)
+//   Linux  各 applications 目录下的 .desktop：Name、Name[xx] 与 Keywords、Keywords[xx]
+// 两类来源在所有平台上都会查找，不存在的目录直接跳过。
+
+use std::collections::HashSet;
+use std::io::Cursor;
+use std::path::Path;
+
+use crate::matcher;
+
+/// 本地化显示名所在的键
+const NAME_KEYS: &[&str] = &["CFBundleDisplayName", "CFBundleName"];
+
+#[derive(Debug, Default, PartialEq)]
+pub struct InstalledApp {
+    /// 应用在文件系统中的名字 (.app 或 .desktop 去掉扩展名)，即搜索结果中出现的名字
+    pub name: String,
+    /// 显示名及其各语言的本地化形式，不含与 `name` 相同的
+    pub display_names: Vec<String>,
+    /// 应用声明的搜索关键词
+    pub keywords: Vec<String>,
+}
+
+impl InstalledApp {
+    fn new(name: &str) -> Self {
+        Self { name: name.to_string(), ..Default::default() }
+    }
+
+    // 折叠后去重，跳过与文件名相同的
+    fn add_name(&mut self, display_name: &str) {
+        let folded = matcher::fold(display_name.trim());
+        if folded.is_empty() || folded == matcher::fold(&self.name) || self.display_names.iter().any(|n| matcher::fold(n) == folded) {
+            return;
+        }
+        self.display_names.push(display_name.trim().to_string());
+    }
+
+    fn add_keyword(&mut self, keyword: &str) {
+        let keyword = keyword.trim();
+        if !keyword.is_empty() && !self.keywords.iter().any(|k| matcher::fold(k) == matcher::fold(keyword)) {
+            self.keywords.push(keyword.to_string());
+        }
+    }
+}
+
+/// 读取 .strings 文件：可能是 UTF-16 或 UTF-8 的文本格式，也可能已编译为二进制或 XML plist
+fn parse_strings(bytes: &[u8]) -> Option<plist::Dictionary> {
+    if bytes.starts_with(b"bplist") || bytes.starts_with(b"<?xml") {
+        return plist::Value::from_reader(Cursor::new(bytes)).ok()?.into_dictionary();
+    }
+    let text = match bytes {
+        [0xFF, 0xFE, rest @ ..] => String::from_utf16(&rest.chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect::<Vec<_>>()).ok()?,
+        [0xFE, 0xFF, rest @ ..] => String::from_utf16(&rest.chunks_exact(2).map(|c| u16::from_be_bytes([c[0], c[1]])).collect::<Vec<_>>()).ok()?,
+        _ => String::from_utf8_lossy(bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(bytes)).into_owned(),
+    };
+    // 文本格式就是省略了外层花括号的 ASCII plist 字典；该格式按字节读取，非 ASCII 字符须写成 \U 转义
+    let mut escaped = String::with_capacity(text.len() + 2);
+    escaped.push('{');
+    for c in text.chars() {
+        if c.is_ascii() {
+            escaped.push(c);
+        } else {
+            c.encode_utf16(&mut [0; 2]).iter().for_each(|unit| escaped.push_str(&format!("\\U{:04x}", unit)));
+        }
+    }
+    escaped.push('}');
+    plist::Value::from_reader_ascii(Cursor::new(escaped)).ok()?.into_dictionary()
+}
+
+fn dictionary_names(dict: &plist::Dictionary, app: &mut InstalledApp) {
+    for key in NAME_KEYS {
+        if let Some(name) = dict.get(key).and_then(plist::Value::as_string) {
+            app.add_name(name);
+        }
+    }
+}
+
+/// 读取 .app 包的显示名与各语言的本地化名称；不是应用包时返回 None
+pub fn bundle_app(path: &Path) -> Option<InstalledApp> {
+    let file_name = path.file_name()?.to_string_lossy();
+    let mut app = InstalledApp::new(file_name.strip_suffix(".app")?);
+    let contents = path.join("Contents");
+    if let Some(info) = plist::Value::from_file(contents.join("Info.plist")).ok().and_then(plist::Value::into_dictionary) {
+        dictionary_names(&info, &mut app);
+    }
+    let Ok(resources) = std::fs::read_dir(contents.join("Resources")) else { return Some(app) };
+    let mut lprojs: Vec<_> = resources.flatten().map(|e| e.path()).filter(|p| p.extension().is_some_and(|e| e == "lproj")).collect();
+    // 目录顺序不固定，排序后显示名的顺序才稳定
+    lprojs.sort();
+    for lproj in lprojs {
+        if let Some(strings) = std::fs::read(lproj.join("InfoPlist.strings")).ok().and_then(|b| parse_strings(&b)) {
+            dictionary_names(&strings, &mut app);
+        }
+    }
+    Some(app)
+}
+
+/// 解析 .desktop 文件；不是应用、或声明为不显示的条目返回 None
+pub fn desktop_entry(content: &str, name: &str) -> Option<InstalledApp> {
+    let mut app = InstalledApp::new(name);
+    let mut in_entry = false;
+    let mut is_application = false;
+    for line in content.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_entry = line == "[Desktop Entry]";
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=').filter(|_| in_entry) else { continue };
+        let (key, value) = (key.trim(), value.trim());
+        // Name[zh_CN]、Keywords[zh_CN] 等本地化键与默认键同样处理
+        let base_key = key.split_once('[').map_or(key, |(base, _)| base);
+        match base_key {
+            "Type" => is_application = value == "Application",
+            "NoDisplay" | "Hidden" if value == "true" => return None,
+            "Name" => app.add_name(value),
+            "Keywords" => value.split(';').for_each(|k| app.add_keyword(k)),
+            _ => {}
+        }
+    }
+    is_application.then_some(app)
+}
+
+/// 当前系统中安装的应用，按名字排序
+pub fn installed_apps() -> Vec<InstalledApp> {
+    let mut apps = bundle_apps(Path::new("/Applications"));
+    apps.extend(desktop_apps());
+    apps.sort_by(|a, b| a.name.cmp(&b.name));
+    apps
+}
+
+fn bundle_apps(dir: &Path) -> Vec<InstalledApp> {
+    let Ok(entries) = std::fs::read_dir(dir) else { return Vec::new() };
+    entries.flatten().filter_map(|e| bundle_app(&e.path())).collect()
+}
+
+fn desktop_apps() -> Vec<InstalledApp> {
+    // 按 XDG 规范，用户目录优先，同名的 .desktop 只取第一个
+    let data_dirs = std::env::var("XDG_DATA_DIRS").ok().filter(|d| !d.is_empty()).unwrap_or_else(|| "/usr/local/share:/usr/share".to_string());
+    let dirs = dirs::data_dir().into_iter().chain(std::env::split_paths(&data_dirs)).map(|d| d.join("applications"));
+    let mut seen = HashSet::new();
+    let mut apps = Vec::new();
+    for dir in dirs {
+        let Ok(entries) = std::fs::read_dir(&dir) else { continue };
+        for path in entries.flatten().map(|e| e.path()) {
+            let Some(name) = path.file_name().and_then(|n| n.to_str()).and_then(|n| n.strip_suffix(".desktop")) else { continue };
+            if !seen.insert(name.to_string()) {
+                continue;
+            }
+            if let Some(app) = std::fs::read_to_string(&path).ok().and_then(|c| desktop_entry(&c, name)) {
+                apps.push(app);
+            }
+        }
+    }
+    apps
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_localized_bundle_names() {
+        let root = std::env::temp_dir().join(format!("xtap-apps-test-{}", std::process::id()));
+        let bundle = root.join("VideoFusion-macOS.app");
+        let resources = bundle.join("Contents/Resources");
+        std::fs::create_dir_all(resources.join("zh-Hans.lproj")).unwrap();
+        std::fs::create_dir_all(resources.join("en.lproj")).unwrap();
+        let info = plist::Dictionary::from_iter([("CFBundleName".to_string(), plist::Value::from("VideoFusion-macOS"))]);
+        plist::Value::Dictionary(info).to_file_xml(bundle.join("Contents/Info.plist")).unwrap();
+        // 文本格式常见为带 BOM 的 UTF-16
+        let text = "/* 本地化 */\n\"CFBundleDisplayName\" = \"剪映专业版\";\nCFBundleName = \"剪映\";\n";
+        let utf16: Vec<u8> = [0xFF, 0xFE].into_iter().chain(text.encode_utf16().flat_map(u16::to_le_bytes)).collect();
+        std::fs::write(resources.join("zh-Hans.lproj/InfoPlist.strings"), utf16).unwrap();
+        let mut compiled = Vec::new();
+        let en = plist::Dictionary::from_iter([("CFBundleDisplayName".to_string(), plist::Value::from("CapCut"))]);
+        plist::Value::Dictionary(en).to_writer_binary(&mut compiled).unwrap();
+        std::fs::write(resources.join("en.lproj/InfoPlist.strings"), compiled).unwrap();
+
+        let app = bundle_app(&bundle).unwrap();
+        assert_eq!(app.name, "VideoFusion-macOS");
+        assert_eq!(app.display_names, ["CapCut", "剪映专业版", "剪映"]);
+        assert!(bundle_app(&root.join("notes.txt")).is_none());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn parses_desktop_entries() {
+        let content = "[Desktop Entry]\nType=Application\nName=Files\nName[zh_CN]=文件\nKeywords=folder;manager;\nKeywords[zh_CN]=文件夹;管理器;\n\n[Desktop Action new-window]\nName=New Window\n";
+        let app = desktop_entry(content, "org.gnome.Nautilus").unwrap();
+        assert_eq!(app.display_names, ["Files", "文件"]);
+        assert_eq!(app.keywords, ["folder", "manager", "文件夹", "管理器"]);
+        assert!(desktop_entry("[Desktop Entry]\nType=Application\nName=Hidden\nNoDisplay=true\n", "hidden").is_none());
+        assert!(desktop_entry("[Desktop Entry]\nType=Link\nName=Site\n", "site").is_none());
+    }
+}
@@ -0,0 +1,114 @@
+// 简繁体等价匹配：把繁体字折叠为简体，使「简历」与「簡歷.docx」互相命中
+//
+// 内置常用字对照表 (覆盖文件名中的高频字)，不追求完整的词级转换。
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+// 繁简对照：每两个字符为一组 (繁体, 简体)
+const PAIRS: &str = "\
+    萬万與与醜丑專专業业叢丛東东絲丝兩两嚴严喪丧個个豐丰臨临為为麗丽舉举麼么義义烏乌樂乐喬乔習习鄉乡\
+    書书買买亂乱爭争於于虧亏雲云亞亚產产畝亩親亲億亿僅仅從从侖仑倉仓儀仪們们價价眾众優优會会傘伞偉伟\
+    傳传傷伤倫伦偽伪體体餘余傭佣俠侠侶侣偵侦側侧僑侨係系倆俩儉俭債债傾倾償偿儲储兒儿兌兑黨党蘭兰關关\
+    興兴茲兹養养獸兽內内岡冈冊册寫写軍军農农馮冯衝冲決决況况凍冻淨净準准涼凉減减湊凑幾几鳳凤憑凭凱凯\
+    擊击劃划劉刘則则剛刚創创刪删別别劑剂劍剑剝剥劇剧勸劝辦办務务動动勵励勁劲勞劳勢势勛勋勝胜區区醫医\
+    華华協协單单賣卖盧卢衛卫卻却廠厂廳厅歷历曆历厲厉壓压厭厌廁厕廂厢廈厦廚厨縣县參参雙双發发髮发變变\
+    敘叙疊叠葉叶號号嘆叹嚇吓呂吕嗎吗噸吨聽听啟启吳吴員员嗚呜詠咏嚨咙響响啞哑嘩哗喚唤團团園园圍围圖图\
+    圓圆聖圣場场壞坏塊块堅坚壇坛壩坝墳坟墜坠壘垒墾垦壺壶壽寿夠够夢梦夾夹奪夺奮奋妝妆婦妇媽妈婁娄嬌娇\
+    娛娱嬰婴嬸婶孫孙學学寧宁寶宝實实寵宠審审憲宪宮宫寬宽賓宾寢寝對对尋寻導导將将爾尔塵尘嘗尝堯尧尷尴\
+    屍尸盡尽儘尽層层屬属屢屡嶼屿歲岁豈岂崗岗嶺岭嶽岳峽峡幣币帥帅師师帳帐帶带幀帧幫帮幹干乾干廣广莊庄\
+    慶庆廬庐庫库應应廟庙龐庞廢废開开異异棄弃張张彌弥彎弯彈弹強强歸归當当錄录彥彦徹彻徑径復复複复憶忆\
+    憂忧懷怀態态憐怜總总戀恋懇恳惡恶惱恼悅悦懸悬悶闷驚惊懼惧慘惨懲惩慚惭慣惯願愿戲戏戰战戶户撲扑執执\
+    擴扩掃扫揚扬擾扰撫抚拋抛搶抢護护報报擔担擬拟擁拥攔拦撥拨擇择掛挂擋挡掙挣擠挤揮挥撈捞損损撿捡換换\
+    據据擲掷攬揽擱搁攪搅攜携攝摄擺摆搖摇攤摊撐撑敵敌斂敛數数齋斋鬥斗斬斩斷断無无舊旧時时曠旷晝昼顯显\
+    晉晋曬晒曉晓暈晕暉晖暫暂術术樸朴機机殺杀雜杂權权條条來来楊杨傑杰極极構构樞枢棗枣槍枪楓枫櫃柜檸柠\
+    柵栅標标棧栈棟栋欄栏樹树棲栖樣样橋桥樺桦檜桧槳桨樁桩檢检樓楼欖榄櫻樱櫥橱橫横歡欢歐欧殲歼殘残毆殴\
+    毀毁畢毕斃毙氣气氫氢匯汇彙汇漢汉湯汤溝沟沒没瀝沥淪沦滄沧滬沪淚泪瀘泸瀉泻潑泼澤泽潔洁灑洒窪洼淺浅\
+    漿浆澆浇濁浊測测濟济瀏浏渾浑濃浓濤涛澇涝漣涟渦涡滌涤潤润澗涧漲涨澀涩澱淀淵渊漬渍漸渐漁渔瀋沈滲渗\
+    溫温灣湾濕湿潰溃濺溅滾滚滯滞滿满濾滤濫滥濱滨灘滩瀟潇潛潜瀾澜瀨濑瀕濒滅灭燈灯靈灵災灾燦灿爐炉燉炖\
+    點点煉炼熾炽爍烁爛烂燭烛煙烟煩烦燒烧燴烩燙烫熱热煥焕愛爱爺爷牘牍牽牵犧牺狀状猶犹狽狈獨独狹狭獅狮\
+    獄狱獵猎豬猪貓猫獻献瑪玛環环現现璽玺瑣琐瑤瑶瑩莹瓏珑甕瓮畫画暢畅療疗瘡疮瘋疯癢痒癡痴癱瘫癮瘾癬癣\
+    癥症皺皱盞盏鹽盐監监蓋盖盜盗盤盘矚瞩睜睁瞞瞒礦矿碼码磚砖硯砚礎础碩硕確确鹼碱礙碍禮礼禱祷禍祸祿禄\
+    禪禅離离禿秃種种積积稱称穢秽穩稳窮穷竊窃竅窍窯窑竄窜窩窝窺窥豎竖競竞筆笔筍笋箋笺籠笼築筑篩筛箏筝\
+    籌筹簽签籤签簡简節节範范篤笃籃篮籬篱簫箫類类糧粮粵粤緊紧糾纠紀纪約约紅红紋纹納纳紐纽純纯紗纱紙纸\
+    級级紛纷紡纺紮扎細细紳绅紹绍終终組组絆绊經经結结絕绝絞绞絡络給给統统絨绒絹绢綁绑繼继續续綜综綠绿\
+    綢绸網网維维綿绵綸纶線线緒绪緞缎締缔緣缘編编緩缓練练緯纬緻致縛缚縫缝縮缩績绩織织繡绣繩绳繪绘繞绕\
+    纏缠纖纤纜缆罰罚罷罢羅罗翹翘聞闻聯联聰聪聲声職职聳耸肅肃腸肠膚肤腎肾腫肿脹胀脅胁膽胆臉脸腦脑膠胶\
+    脈脉臘腊臟脏髒脏艙舱艦舰艱艰藝艺蘆芦蘇苏蘋苹莖茎薦荐藥药莢荚蓮莲獲获穫获薩萨蕭萧薑姜蔔卜蔣蒋蘿萝\
+    螢萤營营蟲虫雖虽蝦虾蠶蚕蠻蛮蠟蜡補补襯衬襖袄裝装製制襪袜見见規规覓觅視视覽览覺觉觀观觸触計计訂订\
+    認认討讨讓让訓训議议訊讯記记講讲許许論论設设訪访證证評评識识詞词譯译試试詩诗誠诚話话誕诞該该詳详\
+    語语誤误說说請请諸诸讀读課课誰谁調调談谈謀谋謎谜謝谢謠谣謹谨譜谱讚赞貝贝負负財财貢贡貧贫貨货販贩\
+    貪贪責责貯贮貴贵貸贷費费賀贺賬账賠赔賭赌賤贱賦赋質质購购賽赛贈赠贊赞資资趙赵趕赶躍跃蹤踪車车軌轨\
+    軟软轉转輪轮較较載载輔辅輕轻輸输輯辑轄辖轎轿辭辞邊边遼辽達达遷迁過过邁迈運运還还這这進进遠远違违\
+    連连遲迟適适選选遺遗遊游郵邮鄭郑鄧邓醬酱釋释裡里裏里針针釘钉釣钓鈕钮鈔钞鈴铃鉛铅銀银銅铜銷销鋒锋\
+    鋼钢錢钱錯错錶表鍵键鍋锅鎖锁鏡镜鐘钟鍾钟鐵铁鑰钥鏈链長长門门閃闪閉闭問问閒闲間间閱阅闆板闊阔闡阐\
+    陣阵陰阴陳陈陸陆陽阳隊队階阶際际隨随險险隱隐雞鸡難难雛雏電电霧雾靜静韓韩頁页頂顶項项順顺須须鬚须\
+    預预頑顽頒颁頓顿領领頭头頻频題题額额顏颜顧顾風风颱台臺台檯台飛飞飯饭飲饮飽饱餅饼館馆饞馋馬马駕驾\
+    駐驻騎骑驗验驅驱驢驴骯肮鬆松鬍胡魚鱼鮮鲜鯨鲸鳥鸟鳴鸣鴨鸭鵝鹅鷹鹰麥麦麵面黃黄齊齐齒齿龍龙龜龟檔档\
+    後后國国週周託托慾欲隻只併并並并佈布採采鬱郁夥伙鐳镭軸轴邏逻嶄崭簾帘壯壮豔艳艷艳顆颗鹹咸嚮向傢家\
+    閣阁闖闯闢辟闕阙鑑鉴鑒鉴鑄铸鑽钻鑼锣鑲镶靂雳靄霭韻韵頸颈顫颤飄飘餓饿餵喂騙骗騰腾驕骄驟骤鬧闹魯鲁\
+    鯉鲤鳩鸠鴿鸽鵬鹏鶴鹤鷺鹭鸚鹦黴霉齡龄";
+
+// 本身也是规范繁体字的简体字 (如 面/麵、后/後、干/乾)，反向转换时保持原样以免误转
+const AMBIGUOUS_SIMPLIFIED: &str = "面后里干台只表板松胡系制复采布并郁余云冲周准欲托汇卜姜苹范症致扎沈划斗几家向咸";
+
+struct Tables {
+    to_simplified: HashMap<char, char>,
+    to_traditional: HashMap<char, char>,
+}
+
+fn tables() -> &'static Tables {
+    static TABLES: OnceLock<Tables> = OnceLock::new();
+    TABLES.get_or_init(|| {
+        let chars: Vec<char> = PAIRS.chars().filter(|c| !c.is_whitespace()).collect();
+        let mut to_simplified = HashMap::new();
+        let mut to_traditional = HashMap::new();
+        for pair in chars.chunks_exact(2) {
+            let (trad, simp) = (pair[0], pair[1]);
+            to_simplified.insert(trad, simp);
+            if !AMBIGUOUS_SIMPLIFIED.contains(simp) {
+                // 一简对多繁时取表中第一个 (最常用) 的写法
+                to_traditional.entry(simp).or_insert(trad);
+            }
+        }
+        Tables { to_simplified, to_traditional }
+    })
+}
+
+fn convert<'a>(s: &'a str, table: &HashMap<char, char>) -> Cow<'a, str> {
+    if s.is_ascii() || !s.chars().any(|c| table.contains_key(&c)) {
+        return Cow::Borrowed(s);
+    }
+    Cow::Owned(s.chars().map(|c| *table.get(&c).unwrap_or(&c)).collect())
+}
+
+/// 繁体转简体，用作简繁等价匹配时的统一比较形式
+pub fn to_simplified(s: &str) -> Cow<'_, str> {
+    convert(s, &tables().to_simplified)
+}
+
+/// 简体转繁体，用于生成 Spotlight 的繁体备选查询
+pub fn to_traditional(s: &str) -> Cow<'_, str> {
+    convert(s, &tables().to_traditional)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn traditional_folds_to_simplified() {
+        assert_eq!(to_simplified("簡歷.docx"), "简历.docx");
+        assert_eq!(to_simplified("會議記錄"), "会议记录");
+        assert!(matches!(to_simplified("简历"), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn ambiguous_simplified_stays_unchanged() {
+        assert_eq!(to_traditional("简历"), "簡歷");
+        // 面 本身是规范繁体字，不能反向转成 麵
+        assert_eq!(to_traditional("面试"), "面試");
+        assert_eq!(to_simplified("麵包"), "面包");
+    }
+}
@@ -0,0 +1,407 @@
+// 静态加密：索引缓存文件用 XChaCha20-Poly1305 分块加密，数据库用 SQLCipher，二者共用本机生成的 256 位密钥
+//
+// 密钥优先存放在系统钥匙串 (macOS 钥匙串、Windows 凭据管理器)；钥匙串不可用 (Linux) 或便携模式下
+// 存在数据目录的 store.key 中，仅当前用户可读写 (0600)。
+//
+// 加密文件格式：魔数 + 19 字节随机 nonce 前缀 + 若干密文块。每块明文 64 KiB，附 16 字节认证标签，
+// 块序号与「是否最后一块」参与 nonce (STREAM 构造)，篡改、调换或截断都会在读取时报错。
+// 没有魔数的文件视为旧版本的明文缓存，照常读取，下次保存时加密。
+
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::stream::{DecryptorBE32, EncryptorBE32};
+use chacha20poly1305::aead::{KeyInit, OsRng};
+use chacha20poly1305::XChaCha20Poly1305;
+use std::io::{self, Cursor, Read, Write};
+use std::path::Path;
+
+use crate::paths::AppDirs;
+
+pub const KEY_LEN: usize = 32;
+const MAGIC: &[u8; 8] = b"XTAPENC1";
+const NONCE_PREFIX_LEN: usize = 19;
+const CHUNK_LEN: usize = 64 * 1024;
+const TAG_LEN: usize = 16;
+const KEY_FILE: &str = "store.key";
+
+fn corrupt() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "解密失败：密钥不匹配或文件已损坏")
+}
+
+#[derive(Clone)]
+pub struct Cipher {
+    key: [u8; KEY_LEN],
+}
+
+impl Cipher {
+    pub fn new(key: [u8; KEY_LEN]) -> Self {
+        Self { key }
+    }
+
+    pub fn generate_key() -> [u8; KEY_LEN] {
+        XChaCha20Poly1305::generate_key(&mut OsRng).into()
+    }
+
+    /// 十六进制形式的密钥，用于备份与恢复
+    pub fn key_hex(&self) -> String {
+        to_hex(&self.key)
+    }
+
+    /// SQLCipher 的原始密钥写法，直接使用该密钥而不再做口令派生
+    pub fn sqlcipher_key(&self) -> String {
+        format!("x'{}'", self.key_hex())
+    }
+
+    fn aead(&self) -> XChaCha20Poly1305 {
+        XChaCha20Poly1305::new(&self.key.into())
+    }
+
+    /// 加密写入；写完后必须调用 `finish`，否则文件不完整，读取时会报错
+    pub fn writer<W: Write>(&self, mut inner: W) -> io::Result<EncryptWriter<W>> {
+        let mut nonce = [0u8; NONCE_PREFIX_LEN];
+        OsRng.fill_bytes(&mut nonce);
+        inner.write_all(MAGIC)?;
+        inner.write_all(&nonce)?;
+        Ok(EncryptWriter {
+            inner,
+            encryptor: Some(EncryptorBE32::from_aead(self.aead(), (&nonce).into())),
+            buf: Vec::with_capacity(CHUNK_LEN),
+        })
+    }
+
+    /// 读取加密文件；没有加密头的旧版明文文件原样读取
+    pub fn reader<'a, R: Read + 'a>(&self, mut inner: R) -> io::Result<Box<dyn Read + 'a>> {
+        let mut head = Vec::with_capacity(MAGIC.len());
+        (&mut inner).take(MAGIC.len() as u64).read_to_end(&mut head)?;
+        if head != MAGIC {
+            return Ok(Box::new(Cursor::new(head).chain(inner)));
+        }
+        let mut nonce = [0u8; NONCE_PREFIX_LEN];
+        inner.read_exact(&mut nonce).map_err(|_| corrupt())?;
+        let next = read_chunk(&mut inner)?;
+        Ok(Box::new(DecryptReader {
+            inner,
+            decryptor: Some(DecryptorBE32::from_aead(self.aead(), (&nonce).into())),
+            next,
+            plain: Vec::new(),
+            pos: 0,
+        }))
+    }
+}
+
+/// 文件是否已是加密格式
+pub fn is_encrypted(path: &Path) -> bool {
+    let mut head = [0u8; MAGIC.len()];
+    std::fs::File::open(path).and_then(|mut f| f.read_exact(&mut head)).is_ok() && &head == MAGIC
+}
+
+pub struct EncryptWriter<W: Write> {
+    inner: W,
+    encryptor: Option<EncryptorBE32<XChaCha20Poly1305>>,
+    buf: Vec<u8>,
+}
+
+impl<W: Write> EncryptWriter<W> {
+    /// 加密并写出最后一块，返回底层写入器
+    pub fn finish(mut self) -> io::Result<W> {
+        let encryptor = self.encryptor.take().expect("finish 只能调用一次");
+        let sealed = encryptor.encrypt_last(self.buf.as_slice()).map_err(|_| io::Error::other("加密失败"))?;
+        self.inner.write_all(&sealed)?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for EncryptWriter<W> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        let n = data.len().min(CHUNK_LEN - self.buf.len());
+        self.buf.extend_from_slice(&data[..n]);
+        if self.buf.len() == CHUNK_LEN {
+            let encryptor = self.encryptor.as_mut().ok_or_else(|| io::Error::other("写入已结束"))?;
+            let sealed = encryptor.encrypt_next(self.buf.as_slice()).map_err(|_| io::Error::other("加密失败"))?;
+            self.inner.write_all(&sealed)?;
+            self.buf.clear();
+        }
+        Ok(n)
+    }
+
+    // 未满一块的数据留到下一块或 finish 时再加密
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+struct DecryptReader<R: Read> {
+    inner: R,
+    decryptor: Option<DecryptorBE32<XChaCha20Poly1305>>,
+    // 预读的下一块密文：读到末尾才知道当前块是不是最后一块
+    next: Vec<u8>,
+    plain: Vec<u8>,
+    pos: usize,
+}
+
+// 读满一个密文块，文件末尾时可能不足一块
+fn read_chunk(inner: &mut impl Read) -> io::Result<Vec<u8>> {
+    let mut chunk = Vec::with_capacity(CHUNK_LEN + TAG_LEN);
+    inner.take((CHUNK_LEN + TAG_LEN) as u64).read_to_end(&mut chunk)?;
+    Ok(chunk)
+}
+
+impl<R: Read> Read for DecryptReader<R> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.plain.len() {
+            let Some(decryptor) = self.decryptor.as_mut() else { return Ok(0) };
+            let current = std::mem::take(&mut self.next);
+            self.next = read_chunk(&mut self.inner)?;
+            self.plain = if self.next.is_empty() {
+                let decryptor = self.decryptor.take().unwrap();
+                decryptor.decrypt_last(current.as_slice())
+            } else {
+                decryptor.decrypt_next(current.as_slice())
+            }
+            .map_err(|_| corrupt())?;
+            self.pos = 0;
+        }
+        let n = out.len().min(self.plain.len() - self.pos);
+        out[..n].copy_from_slice(&self.plain[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// 解析十六进制密钥 (恢复密钥时用户粘贴的内容)
+pub fn parse_key(hex: &str) -> Result<[u8; KEY_LEN], String> {
+    let hex = hex.trim();
+    if hex.len() != KEY_LEN * 2 || !hex.is_ascii() {
+        return Err(format!("密钥应为 {} 位十六进制字符", KEY_LEN * 2));
+    }
+    let mut key = [0u8; KEY_LEN];
+    for (i, byte) in key.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).map_err(|_| "密钥包含非十六进制字符".to_string())?;
+    }
+    Ok(key)
+}
+
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+mod keychain {
+    const KEYCHAIN_SERVICE: &str = "com.xtap.search";
+    const KEYCHAIN_ACCOUNT: &str = "storage-key";
+
+    fn entry() -> Result<keyring::Entry, String> {
+        keyring::Entry::new(KEYCHAIN_SERVICE, KEYCHAIN_ACCOUNT).map_err(|e| e.to_string())
+    }
+
+    pub fn get() -> Result<Option<String>, String> {
+        match entry()?.get_password() {
+            Ok(secret) => Ok(Some(secret)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    pub fn set(secret: &str) -> Result<(), String> {
+        entry()?.set_password(secret).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+mod keychain {
+    // 没有钥匙串，视为其中没有密钥
+    pub fn get() -> Result<Option<String>, String> {
+        Ok(None)
+    }
+
+    pub fn set(_secret: &str) -> Result<(), String> {
+        Err("当前平台不使用钥匙串".to_string())
+    }
+}
+
+fn read_key_file(path: &Path) -> Result<Option<[u8; KEY_LEN]>, String> {
+    match std::fs::read_to_string(path) {
+        Ok(content) => parse_key(&content).map(Some).map_err(|e| format!("密钥文件 {} 无效: {}", path.display(), e)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(format!("无法读取密钥文件: {}", e)),
+    }
+}
+
+fn write_key_file(path: &Path, key: &[u8; KEY_LEN]) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("无法创建数据目录: {}", e))?;
+    }
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        // 文件已存在时 mode 不生效，单独收紧权限
+        if path.exists() {
+            let _ = std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600));
+        }
+    }
+    let mut file = options.open(path).map_err(|e| format!("无法写入密钥文件: {}", e))?;
+    file.write_all(to_hex(key).as_bytes()).map_err(|e| format!("无法写入密钥文件: {}", e))
+}
+
+/// 读取本机的存储密钥，第一次运行时生成。
+/// 钥匙串中没有而数据目录中有密钥文件 (钥匙串曾经不可用) 时，把它移进钥匙串；
+/// 便携模式第一次运行时从系统目录带走数据，也一并带走钥匙串中的密钥。
+/// 只有确认钥匙串中没有密钥时才生成新密钥；钥匙串读取失败 (已锁定、用户取消等) 时返回错误，
+/// 不改动已存的密钥，以免新密钥顶替旧密钥、旧数据库因此打不开
+pub fn load_or_create_key(dirs: &AppDirs) -> Result<[u8; KEY_LEN], String> {
+    load_key_with(dirs, keychain::get, keychain::set)
+}
+
+fn load_key_with(
+    dirs: &AppDirs,
+    keychain_get: impl FnOnce() -> Result<Option<String>, String>,
+    keychain_set: impl FnOnce(&str) -> Result<(), String>,
+) -> Result<[u8; KEY_LEN], String> {
+    let file = dirs.data.join(KEY_FILE);
+    let existing = read_key_file(&file)?;
+    // 便携模式已有密钥文件时不必读钥匙串
+    if let (true, Some(key)) = (dirs.portable, existing) {
+        return Ok(key);
+    }
+    let from_keychain = match (keychain_get(), existing) {
+        (Ok(secret), _) => secret.map(|s| parse_key(&s)).transpose()?,
+        // 密钥文件就是现在的密钥，照常使用，只是这次不移进钥匙串
+        (Err(e), Some(key)) => {
+            eprintln!("无法读取钥匙串: {}，使用密钥文件", e);
+            return Ok(key);
+        }
+        (Err(e), None) => return Err(format!("无法读取钥匙串中的存储密钥: {}", e)),
+    };
+    if dirs.portable {
+        let key = from_keychain.unwrap_or_else(Cipher::generate_key);
+        write_key_file(&file, &key)?;
+        return Ok(key);
+    }
+    if let Some(key) = from_keychain {
+        return Ok(key);
+    }
+    let key = existing.unwrap_or_else(Cipher::generate_key);
+    match keychain_set(&to_hex(&key)) {
+        Ok(()) => {
+            let _ = std::fs::remove_file(&file);
+        }
+        Err(e) => {
+            if cfg!(any(target_os = "macos", target_os = "windows")) {
+                eprintln!("无法写入钥匙串: {}，改用密钥文件", e);
+            }
+            if existing.is_none() {
+                write_key_file(&file, &key)?;
+            }
+        }
+    }
+    Ok(key)
+}
+
+/// 恢复备份的密钥：写入钥匙串，钥匙串不可用时写入密钥文件
+pub fn store_key(dirs: &AppDirs, key: &[u8; KEY_LEN]) -> Result<(), String> {
+    if !dirs.portable && keychain::set(&to_hex(key)).is_ok() {
+        let _ = std::fs::remove_file(dirs.data.join(KEY_FILE));
+        return Ok(());
+    }
+    write_key_file(&dirs.data.join(KEY_FILE), key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encrypt(cipher: &Cipher, data: &[u8]) -> Vec<u8> {
+        let mut writer = cipher.writer(Vec::new()).unwrap();
+        writer.write_all(data).unwrap();
+        writer.finish().unwrap()
+    }
+
+    fn decrypt(cipher: &Cipher, data: &[u8]) -> io::Result<Vec<u8>> {
+        let mut out = Vec::new();
+        cipher.reader(data)?.read_to_end(&mut out)?;
+        Ok(out)
+    }
+
+    #[test]
+    fn round_trips_and_detects_tampering() {
+        let cipher = Cipher::new(Cipher::generate_key());
+        // 空文件、不足一块、恰好整块与跨块
+        for len in [0, 10, CHUNK_LEN, CHUNK_LEN * 2 + 7] {
+            let data: Vec<u8> = (0..len).map(|i| (i % 251) as u8).collect();
+            let sealed = encrypt(&cipher, &data);
+            assert_ne!(&sealed[MAGIC.len()..], &data[..]);
+            assert_eq!(decrypt(&cipher, &sealed).unwrap(), data);
+        }
+
+        let data = vec![7u8; CHUNK_LEN + 100];
+        let sealed = encrypt(&cipher, &data);
+        let mut flipped = sealed.clone();
+        flipped[MAGIC.len() + NONCE_PREFIX_LEN + 5] ^= 1;
+        assert!(decrypt(&cipher, &flipped).is_err());
+        // 截掉最后一块
+        assert!(decrypt(&cipher, &sealed[..MAGIC.len() + NONCE_PREFIX_LEN + CHUNK_LEN + TAG_LEN]).is_err());
+        // 换了密钥
+        assert!(decrypt(&Cipher::new(Cipher::generate_key()), &sealed).is_err());
+    }
+
+    #[test]
+    fn reads_legacy_plaintext() {
+        let cipher = Cipher::new(Cipher::generate_key());
+        assert_eq!(decrypt(&cipher, b"/Applications/Safari.app\n").unwrap(), b"/Applications/Safari.app\n");
+        assert_eq!(decrypt(&cipher, b"/a").unwrap(), b"/a");
+    }
+
+    #[test]
+    fn key_file_is_created_private_and_reused() {
+        let root = std::env::temp_dir().join(format!("xtap-crypto-test-{}", std::process::id()));
+        let dirs = AppDirs::portable(&root);
+        let key = load_or_create_key(&dirs).unwrap();
+        assert_eq!(load_or_create_key(&dirs).unwrap(), key);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(dirs.data.join(KEY_FILE)).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        let restored = Cipher::generate_key();
+        store_key(&dirs, &restored).unwrap();
+        assert_eq!(load_or_create_key(&dirs).unwrap(), restored);
+        assert_eq!(parse_key(&Cipher::new(restored).key_hex()).unwrap(), restored);
+        assert!(parse_key("abc").is_err());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn keychain_errors_never_replace_the_key() {
+        let root = std::env::temp_dir().join(format!("xtap-keychain-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let dirs = AppDirs { portable: false, ..AppDirs::portable(&root) };
+        let locked = || Err("钥匙串已锁定".to_string());
+        let no_set = |_: &str| -> Result<(), String> { panic!("不应写入钥匙串") };
+
+        // 钥匙串读不出来又没有密钥文件：报错，不生成新密钥
+        assert!(load_key_with(&dirs, locked, no_set).is_err());
+        assert!(!dirs.data.join(KEY_FILE).exists());
+
+        // 钥匙串中没有条目时才生成，写入钥匙串失败则存为密钥文件
+        let key = load_key_with(&dirs, || Ok(None), |_| Err("不可用".to_string())).unwrap();
+        assert_eq!(read_key_file(&dirs.data.join(KEY_FILE)).unwrap(), Some(key));
+        // 之后钥匙串出错时沿用密钥文件，不改动它
+        assert_eq!(load_key_with(&dirs, locked, no_set).unwrap(), key);
+        assert_eq!(read_key_file(&dirs.data.join(KEY_FILE)).unwrap(), Some(key));
+
+        // 钥匙串中的密钥优先
+        let stored = Cipher::generate_key();
+        assert_eq!(load_key_with(&dirs, || Ok(Some(to_hex(&stored))), no_set).unwrap(), stored);
+
+        // 便携模式第一次运行时钥匙串出错：不生成新密钥
+        let portable = AppDirs::portable(&root.join("portable"));
+        assert!(load_key_with(&portable, locked, no_set).is_err());
+        assert!(!portable.data.join(KEY_FILE).exists());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,571 @@
+// 点击历史：记录每次打开的时间，排序时按「频率 + 新近度」(frecency) 计算加成
+//
+// 每次打开的贡献随时间指数衰减，经过一个半衰期减半：两年前打开 50 次的文件，
+// 分量不会再压过今天刚用过的文件。每个路径只保留最近的若干次打开时间，更早的只计入总次数。
+//
+// 除了全局的打开记录，还按查询记住用户选了哪个结果 (类似启动器的自适应排序)：
+// 输入 report 后打开某个文件，会同时记在 re、rep、repo ... report 这些前缀下，
+// 之后输入 rep 时这个文件靠前，其它查询不受影响。
+//
+// 早期版本保存在 click_history.json 中，首次启动时导入数据库。最早的格式是「路径 -> 点击次数」，
+// 旧次数视为在迁移时刻 (历史文件最后修改时间) 发生，此后正常衰减。
+//
+// 持久化由后台线程完成：命令只把改动 (HistoryOp) 排入队列，连续的改动合并成一批交给存储层
+// (见 store.rs)，退出时再补写一次。
+//
+// 历史不会无限增长：路径数与查询前缀数各有上限，超出时淘汰衰减后价值最低的，淘汰同样作为改动写入数据库；
+// 每次重新扫描索引后清理已不存在的路径，能唯一对上新位置的 (同名文件) 视为移动，记录随之迁移。
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// 可导入的 click_history.json 最高版本 (3 起记录按查询的选择)
+const FORMAT_VERSION: u32 = 3;
+/// 每个路径保留的打开时间条数，更早的打开衰减后可忽略
+pub const MAX_OPENS: usize = 32;
+/// 按查询学习时记录的前缀长度范围 (字符数)；单个字符太泛，只在整个查询就是一个字时记录
+const MIN_PREFIX_CHARS: usize = 2;
+const MAX_PREFIX_CHARS: usize = 32;
+/// 保留的路径数与查询前缀数上限
+pub const MAX_PATHS: usize = 5000;
+const MAX_QUERY_KEYS: usize = 5000;
+/// 淘汰时衡量价值所用的半衰期
+const EVICTION_HALF_LIFE_SECS: f64 = 30.0 * 86400.0;
+/// 最后一次改动后静默这么久才写入
+const WRITE_DEBOUNCE: Duration = Duration::from_secs(2);
+/// 持续有改动时最长多久写入一次
+const WRITE_MAX_DELAY: Duration = Duration::from_secs(10);
+/// 退出时等待写入完成的最长时间
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// 当前时间 (Unix 秒)
+pub fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+struct PathHistory {
+    /// 最近的打开时间 (Unix 秒)，升序
+    opens: Vec<u64>,
+    /// 打开总次数，含已淘汰的时间与旧格式迁移来的次数
+    total: u32,
+}
+
+impl PathHistory {
+    fn push(&mut self, now: u64) {
+        self.opens.push(now);
+        if self.opens.len() > MAX_OPENS {
+            let excess = self.opens.len() - MAX_OPENS;
+            self.opens.drain(..excess);
+        }
+        self.total = self.total.saturating_add(1);
+    }
+
+    fn merge(&mut self, other: PathHistory) {
+        self.opens.extend(other.opens);
+        self.opens.sort_unstable();
+        if self.opens.len() > MAX_OPENS {
+            let excess = self.opens.len() - MAX_OPENS;
+            self.opens.drain(..excess);
+        }
+        self.total = self.total.saturating_add(other.total);
+    }
+
+    fn decayed(&self, half_life_secs: f64, now: u64) -> f64 {
+        if half_life_secs <= 0.0 {
+            return self.opens.len() as f64;
+        }
+        self.opens
+            .iter()
+            .map(|&t| 0.5f64.powf(now.saturating_sub(t) as f64 / half_life_secs))
+            .sum()
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct HistoryFile {
+    version: u32,
+    paths: HashMap<String, PathHistory>,
+    #[serde(default)]
+    queries: HashMap<String, HashMap<String, PathHistory>>,
+}
+
+#[derive(Clone, Default, Debug)]
+pub struct ClickHistory {
+    paths: HashMap<String, PathHistory>,
+    // 查询前缀 (见 `query::learning_key`) -> 在该查询下选择过的路径
+    queries: HashMap<String, HashMap<String, PathHistory>>,
+}
+
+/// 一次打开：(路径, 时间)
+pub type OpenRecord = (String, u64);
+/// 路径的打开总次数，含不再保留时间的早期打开：(路径, 次数)
+pub type CountRecord = (String, u32);
+/// 在某个查询前缀下的一次选择：(查询前缀, 路径, 时间)
+pub type ChoiceRecord = (String, String, u64);
+
+/// 需要持久化的历史改动，后台写入器成批交给存储层
+#[derive(Clone, Debug, PartialEq)]
+pub enum HistoryOp {
+    /// 打开一次，`prefixes` 为同时学习的查询前缀
+    Open { path: String, prefixes: Vec<String>, at: u64 },
+    Rename { from: String, to: String },
+    Forget { path: String },
+    /// 超出上限而淘汰：`paths` 只删打开记录 (按查询的选择另行淘汰)，`prefixes` 删整个查询前缀
+    Evict { paths: Vec<String>, prefixes: Vec<String> },
+    Clear,
+}
+
+impl HistoryOp {
+    /// 一次打开；带上查询键 (见 `query::learning_key`) 时同时学习「这个查询选了这个结果」
+    pub fn open(path: &str, query_key: Option<&str>, at: u64) -> Self {
+        let prefixes = query_key.map(|k| prefixes(k).map(str::to_string).collect()).unwrap_or_default();
+        Self::Open { path: path.to_string(), prefixes, at }
+    }
+}
+
+// 需要记录的查询前缀：按字符截取，去掉以空白结尾的重复前缀
+fn prefixes(key: &str) -> impl Iterator<Item = &str> {
+    key.char_indices()
+        .map(|(i, c)| i + c.len_utf8())
+        .enumerate()
+        .filter(move |&(n, end)| (n + 1 >= MIN_PREFIX_CHARS || end == key.len()) && n < MAX_PREFIX_CHARS)
+        .map(move |(_, end)| &key[..end])
+        .filter(|p| !p.ends_with(char::is_whitespace))
+}
+
+// 超出上限时淘汰衰减后价值最低的条目，返回被淘汰的键
+fn evict<V>(map: &mut HashMap<String, V>, max: usize, value: impl Fn(&V) -> f64) -> Vec<String> {
+    if map.len() <= max {
+        return Vec::new();
+    }
+    let mut ranked: Vec<(f64, String)> = map.iter().map(|(k, v)| (value(v), k.clone())).collect();
+    ranked.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let evicted: Vec<String> = ranked.into_iter().take(map.len() - max).map(|(_, key)| key).collect();
+    for key in &evicted {
+        map.remove(key);
+    }
+    evicted
+}
+
+/// 从扫描结果中为消失的路径找新位置：文件名在消失的路径与扫描结果中都只出现一次时视为移动
+pub fn detect_moves(missing: &[String], scanned: &[String]) -> Vec<(String, String)> {
+    fn file_name(path: &str) -> &str {
+        path.trim_end_matches('/').rsplit('/').next().unwrap_or(path)
+    }
+    let mut by_name: HashMap<&str, (Option<&str>, usize)> = HashMap::new();
+    for path in missing {
+        let entry = by_name.entry(file_name(path)).or_insert((Some(path), 0));
+        if entry.0 != Some(path.as_str()) {
+            entry.1 = usize::MAX; // 同名的消失路径不止一个，无法判断
+        }
+    }
+    let mut found: HashMap<&str, Option<&str>> = HashMap::new();
+    for path in scanned {
+        let name = file_name(path);
+        if by_name.get(name).is_some_and(|(_, dup)| *dup == 0) {
+            // 第二个同名候选出现后不再唯一
+            found.entry(name).and_modify(|p| *p = None).or_insert(Some(path));
+        }
+    }
+    found
+        .into_iter()
+        .filter_map(|(name, to)| Some((by_name[name].0?.to_string(), to?.to_string())))
+        .collect()
+}
+
+impl ClickHistory {
+    /// 解析历史文件，兼容旧版「路径 -> 点击次数」格式；`legacy_time` 为旧次数的记录时间
+    pub fn parse(content: &str, legacy_time: u64) -> Result<Self, String> {
+        if let Ok(file) = serde_json::from_str::<HistoryFile>(content) {
+            if file.version > FORMAT_VERSION {
+                return Err(format!("点击历史版本过新: {}", file.version));
+            }
+            return Ok(Self { paths: file.paths, queries: file.queries });
+        }
+        let legacy: HashMap<String, u32> = serde_json::from_str(content).map_err(|e| format!("点击历史格式错误: {}", e))?;
+        let paths = legacy
+            .into_iter()
+            .filter(|(_, count)| *count > 0)
+            .map(|(path, count)| {
+                let opens = vec![legacy_time; (count as usize).min(MAX_OPENS)];
+                (path, PathHistory { opens, total: count })
+            })
+            .collect();
+        Ok(Self { paths, queries: HashMap::new() })
+    }
+
+    /// 导出为可再次导入的 JSON (当前版本的历史文件格式)
+    pub fn to_json(&self) -> String {
+        let file = HistoryFile { version: FORMAT_VERSION, paths: self.paths.clone(), queries: self.queries.clone() };
+        serde_json::to_string_pretty(&file).unwrap_or_default()
+    }
+
+    /// 导出为保留的逐次打开记录、各路径的打开总次数与按查询前缀的选择记录
+    pub fn export(&self) -> (Vec<OpenRecord>, Vec<CountRecord>, Vec<ChoiceRecord>) {
+        let mut opens = Vec::new();
+        let mut counts = Vec::new();
+        for (path, history) in &self.paths {
+            opens.extend(history.opens.iter().map(|&t| (path.clone(), t)));
+            counts.push((path.clone(), history.total));
+        }
+        let mut choices = Vec::new();
+        for (prefix, chosen) in &self.queries {
+            for (path, history) in chosen {
+                choices.extend(history.opens.iter().map(|&t| (prefix.clone(), path.clone(), t)));
+            }
+        }
+        (opens, counts, choices)
+    }
+
+    /// 由存储层的记录 (按时间升序) 批量构建，最后统一按上限淘汰一次；
+    /// 同时返回被淘汰的记录，调用方应把它写回存储层
+    pub fn from_records(opens: Vec<OpenRecord>, counts: Vec<CountRecord>, choices: Vec<ChoiceRecord>, now: u64) -> (Self, Option<HistoryOp>) {
+        let mut history = Self::default();
+        for (path, at) in opens {
+            history.paths.entry(path).or_default().push(at);
+        }
+        for (path, total) in counts {
+            let entry = history.paths.entry(path).or_default();
+            entry.total = entry.total.max(total);
+        }
+        history.paths.retain(|_, h| !h.opens.is_empty());
+        for (prefix, path, at) in choices {
+            history.queries.entry(prefix).or_default().entry(path).or_default().push(at);
+        }
+        let evicted = history.enforce_limits(now);
+        (history, evicted)
+    }
+
+    // 路径数与查询前缀数超出上限时淘汰价值最低的，返回需要持久化的淘汰
+    fn enforce_limits(&mut self, now: u64) -> Option<HistoryOp> {
+        let paths = evict(&mut self.paths, MAX_PATHS, |h| h.decayed(EVICTION_HALF_LIFE_SECS, now));
+        let prefixes = evict(&mut self.queries, MAX_QUERY_KEYS, |paths| {
+            paths.values().map(|h| h.decayed(EVICTION_HALF_LIFE_SECS, now)).sum()
+        });
+        (!paths.is_empty() || !prefixes.is_empty()).then_some(HistoryOp::Evict { paths, prefixes })
+    }
+
+    /// 应用一次改动 (与持久化到存储层的是同一份)；因此超出上限时返回淘汰，同样需要持久化
+    pub fn apply(&mut self, op: &HistoryOp) -> Option<HistoryOp> {
+        match op {
+            HistoryOp::Open { path, prefixes, at } => {
+                self.paths.entry(path.to_string()).or_default().push(*at);
+                for prefix in prefixes {
+                    self.queries.entry(prefix.to_string()).or_default().entry(path.to_string()).or_default().push(*at);
+                }
+                return self.enforce_limits(*at);
+            }
+            HistoryOp::Rename { from, to } => self.rename(from, to),
+            HistoryOp::Forget { path } => {
+                self.forget(path);
+            }
+            HistoryOp::Evict { paths, prefixes } => {
+                for path in paths {
+                    self.paths.remove(path);
+                }
+                for prefix in prefixes {
+                    self.queries.remove(prefix);
+                }
+            }
+            HistoryOp::Clear => self.clear(),
+        }
+        None
+    }
+
+    pub fn len(&self) -> usize {
+        self.paths.len()
+    }
+
+    /// 记录一次打开 (不学习查询)，返回该路径的打开总次数
+    #[cfg(test)]
+    pub fn record(&mut self, path: &str, now: u64) -> u32 {
+        self.apply(&HistoryOp::open(path, None, now));
+        self.total(path)
+    }
+
+    /// 所有记录过的路径 (含只在按查询学习中出现的)
+    pub fn paths(&self) -> Vec<String> {
+        let mut paths: Vec<String> = self.paths.keys().cloned().collect();
+        for chosen in self.queries.values() {
+            paths.extend(chosen.keys().filter(|p| !self.paths.contains_key(*p)).cloned());
+        }
+        paths.sort_unstable();
+        paths.dedup();
+        paths
+    }
+
+    /// 文件移动后把记录迁移到新路径 (新路径已有记录时合并)
+    pub fn rename(&mut self, from: &str, to: &str) {
+        if let Some(history) = self.paths.remove(from) {
+            self.paths.entry(to.to_string()).or_default().merge(history);
+        }
+        for chosen in self.queries.values_mut() {
+            if let Some(history) = chosen.remove(from) {
+                chosen.entry(to.to_string()).or_default().merge(history);
+            }
+        }
+    }
+
+    /// 删除一个路径的全部记录，返回是否有记录
+    pub fn forget(&mut self, path: &str) -> bool {
+        let mut found = self.paths.remove(path).is_some();
+        self.queries.retain(|_, chosen| {
+            found |= chosen.remove(path).is_some();
+            !chosen.is_empty()
+        });
+        found
+    }
+
+    pub fn clear(&mut self) {
+        self.paths.clear();
+        self.queries.clear();
+    }
+
+    /// 打开总次数 (不衰减)
+    pub fn total(&self, path: &str) -> u32 {
+        self.paths.get(path).map_or(0, |h| h.total)
+    }
+
+    /// 衰减后的打开次数：刚发生的一次计 1，每经过 `half_life_secs` 减半
+    pub fn frecency(&self, path: &str, half_life_secs: f64, now: u64) -> f64 {
+        self.paths.get(path).map_or(0.0, |h| h.decayed(half_life_secs, now))
+    }
+
+    /// 在查询 `key` (或以它开头的查询) 下选择 `path` 的衰减次数
+    pub fn learned(&self, key: &str, path: &str, half_life_secs: f64, now: u64) -> f64 {
+        self.queries.get(key).and_then(|paths| paths.get(path)).map_or(0.0, |h| h.decayed(half_life_secs, now))
+    }
+}
+
+enum WriterMsg {
+    Op(HistoryOp),
+    /// 立即写入，完成后回复
+    Flush(mpsc::Sender<()>),
+}
+
+/// 点击历史的后台写入器，可在多个命令间共享
+#[derive(Clone)]
+pub struct HistoryWriter {
+    tx: mpsc::Sender<WriterMsg>,
+}
+
+impl HistoryWriter {
+    /// 启动写入线程，攒够一批改动后交给 `write` (在写入线程上调用)
+    pub fn spawn(write: impl FnMut(&[HistoryOp]) -> Result<(), String> + Send + 'static) -> Self {
+        Self::spawn_with_delays(write, WRITE_DEBOUNCE, WRITE_MAX_DELAY)
+    }
+
+    // 静默 `debounce` 或累计等待 `max_delay` 后写入一批
+    fn spawn_with_delays(write: impl FnMut(&[HistoryOp]) -> Result<(), String> + Send + 'static, debounce: Duration, max_delay: Duration) -> Self {
+        let (tx, rx) = mpsc::channel();
+        let spawned = std::thread::Builder::new()
+            .name("click-history-writer".to_string())
+            .spawn(move || write_loop(write, rx, debounce, max_delay));
+        if let Err(e) = spawned {
+            eprintln!("无法启动点击历史写入线程: {}", e);
+        }
+        Self { tx }
+    }
+
+    /// 排入一次改动，立即返回
+    pub fn submit(&self, op: HistoryOp) {
+        let _ = self.tx.send(WriterMsg::Op(op));
+    }
+
+    /// 立即写入排队中的改动并等待完成 (最多 `FLUSH_TIMEOUT`)，用于退出前
+    pub fn flush(&self) {
+        self.flush_within(FLUSH_TIMEOUT);
+    }
+
+    // 返回写入线程是否在 `timeout` 内确认写完
+    fn flush_within(&self, timeout: Duration) -> bool {
+        let (done_tx, done_rx) = mpsc::channel();
+        self.tx.send(WriterMsg::Flush(done_tx)).is_ok() && done_rx.recv_timeout(timeout).is_ok()
+    }
+}
+
+fn write_loop(mut write: impl FnMut(&[HistoryOp]) -> Result<(), String>, rx: mpsc::Receiver<WriterMsg>, debounce: Duration, max_delay: Duration) {
+    while let Ok(msg) = rx.recv() {
+        let mut ops = Vec::new();
+        let mut waiters = Vec::new();
+        match msg {
+            WriterMsg::Flush(done) => waiters.push(done),
+            WriterMsg::Op(op) => {
+                ops.push(op);
+                // 合并后续改动，直到静默 debounce 或累计等待 max_delay
+                let first = Instant::now();
+                loop {
+                    let wait = debounce.min(max_delay.saturating_sub(first.elapsed()));
+                    match rx.recv_timeout(wait) {
+                        Ok(WriterMsg::Op(op)) => ops.push(op),
+                        Ok(WriterMsg::Flush(done)) => {
+                            waiters.push(done);
+                            break;
+                        }
+                        Err(RecvTimeoutError::Timeout | RecvTimeoutError::Disconnected) => break,
+                    }
+                }
+            }
+        }
+
+        if !ops.is_empty() {
+            if let Err(e) = write(&ops) {
+                eprintln!("保存点击历史失败: {}", e);
+            }
+        }
+        for done in waiters {
+            let _ = done.send(());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: u64 = 86400;
+
+    #[test]
+    fn recent_opens_outweigh_old_ones() {
+        let now = 1_000 * DAY;
+        let mut history = ClickHistory::default();
+        for _ in 0..50 {
+            history.record("/old/report.pdf", now - 730 * DAY);
+        }
+        history.record("/new/report.pdf", now);
+        let half_life = 30.0 * DAY as f64;
+        assert!(history.frecency("/new/report.pdf", half_life, now) > history.frecency("/old/report.pdf", half_life, now));
+        assert_eq!(history.total("/old/report.pdf"), 50);
+        // 超出保留条数的打开只计入总次数
+        assert!((history.frecency("/old/report.pdf", 0.0, now) - MAX_OPENS as f64).abs() < 1e-9);
+
+        history.record("/half/life.txt", now - 30 * DAY);
+        assert!((history.frecency("/half/life.txt", half_life, now) - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn learns_choices_per_query_prefix() {
+        let now = 100 * DAY;
+        let mut history = ClickHistory::default();
+        history.apply(&HistoryOp::open("/docs/report-2024.pdf", Some("annual report"), now));
+        history.apply(&HistoryOp::open("/docs/readme.md", Some("r"), now));
+
+        assert_eq!(history.learned("rep", "/docs/report-2024.pdf", 0.0, now), 0.0);
+        assert_eq!(history.learned("annual rep", "/docs/report-2024.pdf", 0.0, now), 1.0);
+        assert_eq!(history.learned("an", "/docs/report-2024.pdf", 0.0, now), 1.0);
+        // 单字前缀不记录，除非整个查询就是一个字；以空格结尾的前缀与去掉空格的重复
+        assert_eq!(history.learned("a", "/docs/report-2024.pdf", 0.0, now), 0.0);
+        assert_eq!(history.learned("annual ", "/docs/report-2024.pdf", 0.0, now), 0.0);
+        assert_eq!(history.learned("r", "/docs/readme.md", 0.0, now), 1.0);
+        // 其它查询不受影响
+        assert_eq!(history.learned("budget", "/docs/report-2024.pdf", 0.0, now), 0.0);
+        assert_eq!(history.total("/docs/report-2024.pdf"), 1);
+    }
+
+    #[test]
+    fn caps_evict_least_valuable() {
+        let now = 100 * DAY;
+        let mut history = ClickHistory::default();
+        history.record("/keep/often.txt", now);
+        history.record("/keep/often.txt", now);
+        for i in 0..MAX_PATHS {
+            history.record(&format!("/old/{}.txt", i), now - 60 * DAY);
+        }
+        assert_eq!(history.len(), MAX_PATHS);
+        assert_eq!(history.total("/keep/often.txt"), 2);
+
+        // 淘汰作为改动返回，应用到另一份历史上结果相同
+        let Some(HistoryOp::Evict { paths, prefixes }) = history.apply(&HistoryOp::open("/new.txt", None, now)) else {
+            panic!("超出上限时应返回淘汰");
+        };
+        assert_eq!((paths.len(), prefixes.len()), (1, 0));
+        assert!(paths[0].starts_with("/old/") && history.total(&paths[0]) == 0);
+        assert_eq!(history.len(), MAX_PATHS);
+
+        // 批量载入时只在最后淘汰一次
+        let (opens, counts, choices) = history.export();
+        let mut opens_with_extra = opens.clone();
+        opens_with_extra.push(("/extra.txt".to_string(), now - 90 * DAY));
+        let (loaded, evicted) = ClickHistory::from_records(opens_with_extra, counts, choices, now);
+        assert_eq!(loaded.len(), MAX_PATHS);
+        assert_eq!(evicted, Some(HistoryOp::Evict { paths: vec!["/extra.txt".to_string()], prefixes: Vec::new() }));
+        assert_eq!(loaded.total("/keep/often.txt"), 2);
+    }
+
+    #[test]
+    fn moves_forgets_and_clears() {
+        let now = 100 * DAY;
+        let mut history = ClickHistory::default();
+        history.apply(&HistoryOp::open("/docs/a/plan.key", Some("plan"), now));
+        history.record("/docs/notes.txt", now);
+        history.record("/docs/x/readme.md", now);
+        history.record("/docs/y/readme.md", now);
+
+        let missing = vec!["/docs/a/plan.key".to_string(), "/docs/x/readme.md".to_string(), "/docs/y/readme.md".to_string()];
+        let scanned = vec!["/docs/b/plan.key".to_string(), "/docs/z/readme.md".to_string(), "/docs/notes.txt".to_string()];
+        // 同名的消失路径不止一个时不做迁移
+        let moves = detect_moves(&missing, &scanned);
+        assert_eq!(moves, vec![("/docs/a/plan.key".to_string(), "/docs/b/plan.key".to_string())]);
+
+        history.rename("/docs/a/plan.key", "/docs/b/plan.key");
+        assert_eq!(history.total("/docs/b/plan.key"), 1);
+        assert_eq!(history.learned("plan", "/docs/b/plan.key", 0.0, now), 1.0);
+        assert_eq!(history.total("/docs/a/plan.key"), 0);
+
+        assert!(history.forget("/docs/b/plan.key"));
+        assert_eq!(history.learned("plan", "/docs/b/plan.key", 0.0, now), 0.0);
+        assert!(!history.forget("/docs/b/plan.key"));
+        assert_eq!(history.paths(), vec!["/docs/notes.txt", "/docs/x/readme.md", "/docs/y/readme.md"]);
+
+        history.clear();
+        assert_eq!(history.len(), 0);
+    }
+
+    #[test]
+    fn writer_batches_ops_and_flushes() {
+        let (batches_tx, batches_rx) = mpsc::channel();
+        // 静默期足够长，写入只可能由 flush 触发，不依赖测试运行的快慢
+        let hour = Duration::from_secs(3600);
+        let writer = HistoryWriter::spawn_with_delays(move |ops| {
+            let _ = batches_tx.send(ops.to_vec());
+            Ok(())
+        }, hour, hour);
+        for i in 0..100 {
+            writer.submit(HistoryOp::open(&format!("/docs/{}.txt", i), None, 100 * DAY));
+        }
+        assert!(batches_rx.try_recv().is_err());
+        // flush 返回 true 时写入已完成，批次已在通道中
+        assert!(writer.flush_within(Duration::MAX));
+        let batch = batches_rx.try_recv().unwrap();
+        assert_eq!(batch.len(), 100);
+        assert!(batches_rx.try_recv().is_err());
+
+        // 没有排队的改动时 flush 不写入
+        assert!(writer.flush_within(Duration::MAX));
+        assert!(batches_rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn migrates_legacy_counts() {
+        let history = ClickHistory::parse(r#"{"/Applications/WeChat.app": 3, "/tmp/x": 0}"#, 500 * DAY).unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history.total("/Applications/WeChat.app"), 3);
+        assert!((history.frecency("/Applications/WeChat.app", 10.0 * DAY as f64, 510 * DAY) - 1.5).abs() < 1e-9);
+
+        let (opens, counts, choices) = history.export();
+        assert_eq!(opens, vec![("/Applications/WeChat.app".to_string(), 500 * DAY); 3]);
+        assert_eq!(counts, vec![("/Applications/WeChat.app".to_string(), 3)]);
+        assert!(choices.is_empty());
+        assert!(ClickHistory::parse("[1, 2]", 0).is_err());
+
+        // 导出的 JSON 可以原样导入
+        let mut history = history;
+        history.apply(&HistoryOp::open("/docs/plan.key", Some("plan"), 501 * DAY));
+        let reimported = ClickHistory::parse(&history.to_json(), 0).unwrap();
+        assert_eq!(reimported.total("/Applications/WeChat.app"), 3);
+        assert_eq!(reimported.learned("plan", "/docs/plan.key", 0.0, 501 * DAY), 1.0);
+    }
+}
@@ -0,0 +1,598 @@
+// 内存索引：路径列 + 预折叠的比较键列，查询时按 CPU 核数分片并行扫描
+//
+// 比较键是按默认规则 (NFC + 全角转半角 + 大小写折叠 + 繁转简) 折叠后的完整路径，
+// 文件名是其后缀；另存文件名与路径的字符位图，绝大多数不相关的条目只需一次位运算即可排除。
+// 所有键拼接在一块连续内存中，百万级索引也只多占一份路径大小的内存。
+//
+// 重新扫描磁盘后按差异增量更新：删除的条目只打标记、新条目追加在末尾，下标保持稳定，
+// 三元组索引 (见 trigram.rs) 因此可以随之增量维护；删除标记过多时整体重建。
+
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::ops::Range;
+use std::path::Path;
+
+use crate::crypto::Cipher;
+use crate::matcher;
+use crate::query::{Folded, Query, SearchOptions};
+use crate::session::CancelToken;
+use crate::trigram::TrigramIndex;
+
+/// 流式模式下每批推送的结果数
+pub const STREAM_BATCH_SIZE: usize = 500;
+/// 模糊命中最多保留的条数 (每个分片)
+const MAX_FUZZY: usize = 1000;
+/// 部分匹配最多保留的条数 (每个分片)，只作为结果太少时的补充
+const MAX_PARTIAL: usize = 1000;
+/// 候选集的上限，超过时整体放弃，下一次查询回到全量扫描
+const MAX_CANDIDATES: usize = 100_000;
+/// 每个分片的最少条目数，条目太少时不值得开线程
+const MIN_CHUNK: usize = 32 * 1024;
+/// 每扫描这么多条目检查一次取消标记
+const CANCEL_CHECK_INTERVAL: usize = 4096;
+/// 条目数达到此规模时自动启用三元组索引，可用环境变量 XTAP_TRIGRAM_INDEX=1/0 强制开关
+const TRIGRAM_MIN_ENTRIES: usize = 1_000_000;
+
+pub struct FileIndex {
+    paths: Vec<String>,
+    keys: String,
+    // 第 i 条的键为 keys[key_ends[i - 1]..key_ends[i]]
+    key_ends: Vec<usize>,
+    // 文件名在键中的起始字节
+    name_starts: Vec<u32>,
+    name_masks: Vec<u64>,
+    path_masks: Vec<u64>,
+    // 已删除的条目保留下标，扫描时跳过
+    removed: Vec<bool>,
+    removed_count: usize,
+    trigrams: Option<TrigramIndex>,
+    // 条目数达到此值时启用三元组索引，重建时沿用
+    trigram_threshold: usize,
+}
+
+impl Default for FileIndex {
+    fn default() -> Self {
+        Self::new(Vec::new(), trigram_threshold())
+    }
+}
+
+/// 重新扫描磁盘后对索引的更新方式
+pub enum IndexUpdate {
+    /// 增量更新：标记删除的下标与新增的路径
+    Changes { removed: Vec<usize>, added: Vec<String> },
+    /// 变化太大，整体替换为新建的索引
+    Rebuild(Box<FileIndex>),
+}
+
+/// 一次扫描的命中结果 (索引下标，按索引顺序)
+#[derive(Default)]
+pub struct ScanHits {
+    /// 所有词项严格命中
+    pub full: Vec<usize>,
+    /// 仅模糊命中
+    pub fuzzy: Vec<usize>,
+    /// 多词查询中只命中了部分词
+    pub partial: Vec<usize>,
+    /// 以任意方式命中的全部条目，供下一次细化查询复用
+    pub candidates: Vec<usize>,
+    /// 候选条目超过 `MAX_CANDIDATES`，`candidates` 已清空，不能用于细化查询
+    pub candidates_truncated: bool,
+}
+
+impl ScanHits {
+    fn append(&mut self, mut other: ScanHits) {
+        self.full.append(&mut other.full);
+        self.fuzzy.append(&mut other.fuzzy);
+        self.partial.append(&mut other.partial);
+        self.candidates.append(&mut other.candidates);
+        self.candidates_truncated |= other.candidates_truncated;
+    }
+}
+
+// 单个分片的比较键
+struct KeyChunk {
+    keys: String,
+    key_ends: Vec<usize>,
+    name_starts: Vec<u32>,
+    name_masks: Vec<u64>,
+    path_masks: Vec<u64>,
+}
+
+fn build_keys(paths: &[String]) -> KeyChunk {
+    let options = SearchOptions::default();
+    let mut chunk = KeyChunk {
+        keys: String::new(),
+        key_ends: Vec::with_capacity(paths.len()),
+        name_starts: Vec::with_capacity(paths.len()),
+        name_masks: Vec::with_capacity(paths.len()),
+        path_masks: Vec::with_capacity(paths.len()),
+    };
+    for path in paths {
+        // 折叠逐字符进行且 '/' 不参与组合，目录与文件名分别折叠后拼接等价于折叠整条路径
+        let start = chunk.keys.len();
+        let name = match path.rsplit_once('/') {
+            Some((dir, name)) => {
+                chunk.keys.push_str(&options.fold_text(dir, true));
+                chunk.keys.push('/');
+                name
+            }
+            None => path.as_str(),
+        };
+        let name_start = chunk.keys.len() - start;
+        chunk.keys.push_str(&options.fold_text(name, true));
+        chunk.name_masks.push(matcher::char_mask(&chunk.keys[start + name_start..]));
+        chunk.path_masks.push(matcher::char_mask(&chunk.keys[start..]));
+        chunk.name_starts.push(name_start as u32);
+        chunk.key_ends.push(chunk.keys.len());
+    }
+    chunk
+}
+
+/// 启用三元组索引的默认条目数，环境变量 XTAP_TRIGRAM_INDEX=1/0 可强制开关
+pub fn trigram_threshold() -> usize {
+    match std::env::var("XTAP_TRIGRAM_INDEX").as_deref() {
+        Ok("1") => 0,
+        Ok("0") => usize::MAX,
+        _ => TRIGRAM_MIN_ENTRIES,
+    }
+}
+
+// 索引缓存内容的校验和 (FNV-1a)，用于确认三元组索引文件与缓存对应
+fn checksum(hash: u64, line: &str) -> u64 {
+    line.bytes().chain(std::iter::once(b'\n')).fold(hash, |h, b| (h ^ b as u64).wrapping_mul(0x100_0000_01b3))
+}
+const CHECKSUM_SEED: u64 = 0xcbf2_9ce4_8422_2325;
+
+fn trigram_path(cache: &Path) -> std::path::PathBuf {
+    cache.with_extension("trigram")
+}
+
+// 把 0..total 均分给各线程
+fn split(total: usize) -> Vec<Range<usize>> {
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk = total.div_ceil(threads).max(MIN_CHUNK);
+    (0..total).step_by(chunk).map(|begin| begin..(begin + chunk).min(total)).collect()
+}
+
+impl FileIndex {
+    /// 条目数达到 `trigram_threshold` 时同时建立三元组索引
+    pub fn new(paths: Vec<String>, trigram_threshold: usize) -> Self {
+        let mut index = Self::from_paths(paths, trigram_threshold);
+        if index.len() >= trigram_threshold {
+            index.build_trigrams();
+        }
+        index
+    }
+
+    fn from_paths(paths: Vec<String>, trigram_threshold: usize) -> Self {
+        let ranges = split(paths.len());
+        let chunks: Vec<KeyChunk> = std::thread::scope(|s| {
+            let handles: Vec<_> = ranges.iter().map(|r| {
+                let part = &paths[r.clone()];
+                s.spawn(move || build_keys(part))
+            }).collect();
+            handles.into_iter().map(|h| h.join().expect("构建索引键失败")).collect()
+        });
+
+        let total_len = chunks.iter().map(|c| c.keys.len()).sum();
+        let mut index = Self {
+            keys: String::with_capacity(total_len),
+            key_ends: Vec::with_capacity(paths.len()),
+            name_starts: Vec::with_capacity(paths.len()),
+            name_masks: Vec::with_capacity(paths.len()),
+            path_masks: Vec::with_capacity(paths.len()),
+            removed: vec![false; paths.len()],
+            removed_count: 0,
+            trigrams: None,
+            trigram_threshold,
+            paths,
+        };
+        for chunk in chunks {
+            index.append_keys(chunk);
+        }
+        index
+    }
+
+    fn append_keys(&mut self, chunk: KeyChunk) {
+        let offset = self.keys.len();
+        self.keys.push_str(&chunk.keys);
+        self.key_ends.extend(chunk.key_ends.iter().map(|e| e + offset));
+        self.name_starts.extend(chunk.name_starts);
+        self.name_masks.extend(chunk.name_masks);
+        self.path_masks.extend(chunk.path_masks);
+    }
+
+    fn build_trigrams(&mut self) {
+        let start = std::time::Instant::now();
+        let mut trigrams = TrigramIndex::default();
+        for i in (0..self.len()).filter(|&i| !self.removed[i]) {
+            let folded = self.folded(i);
+            trigrams.insert(i as u32, &folded.path[..folded.path.len() - folded.name.len()], folded.name);
+        }
+        println!("三元组索引构建完成，耗时: {:?}", start.elapsed());
+        self.trigrams = Some(trigrams);
+    }
+
+    /// 从缓存文件加载索引，空行表示已删除的条目；条目数达到 `trigram_threshold` 时
+    /// 三元组索引文件与缓存匹配则直接加载，否则重建
+    pub fn load(cache: &Path, cipher: &Cipher, trigram_threshold: usize) -> io::Result<Self> {
+        let reader = BufReader::new(cipher.reader(File::open(cache)?)?);
+        let mut paths = Vec::new();
+        let mut removed = Vec::new();
+        let mut hash = CHECKSUM_SEED;
+        // 解密失败 (密钥不匹配或文件损坏) 时整体报错，不使用读到一半的内容
+        for line in reader.lines() {
+            let line = line?;
+            hash = checksum(hash, &line);
+            removed.push(line.is_empty());
+            paths.push(line);
+        }
+
+        let mut index = Self::from_paths(paths, trigram_threshold);
+        index.removed_count = removed.iter().filter(|&&r| r).count();
+        index.removed = removed;
+        if index.len() >= trigram_threshold {
+            let loaded = File::open(trigram_path(cache))
+                .and_then(|f| cipher.reader(f))
+                .and_then(|f| TrigramIndex::load(&mut BufReader::new(f), index.len() as u64, hash));
+            match loaded {
+                Ok(Some(trigrams)) => index.trigrams = Some(trigrams),
+                _ => index.build_trigrams(),
+            }
+        }
+        Ok(index)
+    }
+
+    /// 加密写入缓存文件 (已删除的条目写为空行以保持下标)，启用时一并写入三元组索引
+    pub fn save(&self, cache: &Path, cipher: &Cipher) -> io::Result<()> {
+        let mut out = BufWriter::new(cipher.writer(File::create(cache)?)?);
+        let mut hash = CHECKSUM_SEED;
+        for (path, &removed) in self.paths.iter().zip(&self.removed) {
+            let line = if removed { "" } else { path.as_str() };
+            hash = checksum(hash, line);
+            writeln!(out, "{}", line)?;
+        }
+        out.into_inner().map_err(|e| e.into_error())?.finish()?;
+
+        let sidecar = trigram_path(cache);
+        match self.trigrams.as_ref() {
+            Some(trigrams) => {
+                let mut out = BufWriter::new(cipher.writer(File::create(sidecar)?)?);
+                trigrams.save(&mut out, self.len() as u64, hash)?;
+                out.into_inner().map_err(|e| e.into_error())?.finish().map(|_| ())
+            }
+            None => {
+                let _ = std::fs::remove_file(sidecar);
+                Ok(())
+            }
+        }
+    }
+
+    /// 与重新扫描得到的完整路径列表比对，决定增量更新还是重建 (只读，可在读锁下进行)
+    pub fn plan_update(&self, paths: Vec<String>) -> IndexUpdate {
+        let (removed, added) = {
+            let fresh: HashSet<&str> = paths.iter().map(String::as_str).collect();
+            let current: HashSet<&str> = (0..self.len()).filter(|&i| !self.removed[i]).map(|i| self.path(i)).collect();
+            let removed: Vec<usize> = (0..self.len()).filter(|&i| !self.removed[i] && !fresh.contains(self.path(i))).collect();
+            let added: Vec<String> = paths.iter().filter(|p| !current.contains(p.as_str())).cloned().collect();
+            (removed, added)
+        };
+        // 新增超过现有的四分之一时批量重建更快；删除标记超过四分之一时重建以回收空间
+        if added.len() * 4 > self.count() || (self.removed_count + removed.len()) * 4 > self.len() + added.len() {
+            return IndexUpdate::Rebuild(Box::new(FileIndex::new(paths, self.trigram_threshold)));
+        }
+        IndexUpdate::Changes { removed, added }
+    }
+
+    pub fn apply_update(&mut self, update: IndexUpdate) {
+        match update {
+            IndexUpdate::Changes { removed, added } => {
+                println!("增量更新索引: 删除 {} 条, 新增 {} 条", removed.len(), added.len());
+                for i in removed {
+                    self.remove(i);
+                }
+                for path in added {
+                    self.insert(path);
+                }
+            }
+            IndexUpdate::Rebuild(index) => *self = *index,
+        }
+    }
+
+    /// 在末尾追加一个条目，三元组索引同步更新
+    pub fn insert(&mut self, path: String) {
+        let id = self.len();
+        self.append_keys(build_keys(std::slice::from_ref(&path)));
+        self.paths.push(path);
+        self.removed.push(false);
+        if let Some(trigrams) = self.trigrams.as_mut() {
+            let start = if id == 0 { 0 } else { self.key_ends[id - 1] };
+            let key = &self.keys[start..self.key_ends[id]];
+            let (dir, name) = key.split_at(self.name_starts[id] as usize);
+            trigrams.insert(id as u32, dir, name);
+        }
+    }
+
+    /// 标记删除一个条目，下标保持不变
+    pub fn remove(&mut self, i: usize) {
+        if i < self.len() && !self.removed[i] {
+            self.removed[i] = true;
+            self.removed_count += 1;
+        }
+    }
+
+    /// 三元组索引圈定的、可能严格命中查询的条目；未启用或查询词太短时返回 None
+    pub fn shortlist(&self, query: &Query) -> Option<Vec<usize>> {
+        let candidates = self.trigrams.as_ref()?.candidates(query)?;
+        Some(candidates.into_iter().map(|i| i as usize).filter(|&i| !self.removed[i]).collect())
+    }
+
+    /// 下标空间大小 (含已删除的条目)
+    pub fn len(&self) -> usize {
+        self.paths.len()
+    }
+
+    /// 有效条目数
+    pub fn count(&self) -> usize {
+        self.len() - self.removed_count
+    }
+
+    pub fn path(&self, i: usize) -> &str {
+        &self.paths[i]
+    }
+
+    pub fn name(&self, i: usize) -> &str {
+        let path = &self.paths[i];
+        path.rsplit('/').next().unwrap_or(path)
+    }
+
+    /// 折叠后的完整路径 (已转小写)
+    pub fn key(&self, i: usize) -> &str {
+        let start = if i == 0 { 0 } else { self.key_ends[i - 1] };
+        &self.keys[start..self.key_ends[i]]
+    }
+
+    fn folded(&self, i: usize) -> Folded<'_> {
+        let path = self.key(i);
+        Folded { name: &path[self.name_starts[i] as usize..], path, name_mask: self.name_masks[i], path_mask: self.path_masks[i] }
+    }
+
+    /// 并行扫描索引。`subset` 为 Some 时只检查其中的下标 (递进搜索)；
+    /// `accept(path, key)` 做类型、外接盘等预过滤；`on_full` 在各线程中分批收到严格命中的下标 (流式推送)。
+    pub fn scan<A, F>(&self, query: &Query, subset: Option<&[usize]>, cancel: &CancelToken, accept: A, on_full: F) -> ScanHits
+    where
+        A: Fn(&str, &str) -> bool + Sync,
+        F: Fn(&[usize]) + Sync,
+    {
+        let total = subset.map_or(self.len(), <[usize]>::len);
+        let ranges = split(total);
+        let (accept, on_full) = (&accept, &on_full);
+        let parts: Vec<ScanHits> = std::thread::scope(|s| {
+            let handles: Vec<_> = ranges.into_iter().map(|r| {
+                s.spawn(move || self.scan_range(query, subset, r, cancel, accept, on_full))
+            }).collect();
+            handles.into_iter().map(|h| h.join().unwrap_or_default()).collect()
+        });
+
+        // 按分片顺序合并，结果顺序与单线程扫描一致
+        let mut hits = ScanHits::default();
+        for part in parts {
+            hits.append(part);
+        }
+        hits.fuzzy.truncate(MAX_FUZZY);
+        hits.partial.truncate(MAX_PARTIAL);
+        if hits.candidates_truncated || hits.candidates.len() > MAX_CANDIDATES {
+            hits.candidates = Vec::new();
+            hits.candidates_truncated = true;
+        }
+        hits
+    }
+
+    fn scan_range<A, F>(&self, query: &Query, subset: Option<&[usize]>, range: Range<usize>, cancel: &CancelToken, accept: &A, on_full: &F) -> ScanHits
+    where
+        A: Fn(&str, &str) -> bool,
+        F: Fn(&[usize]),
+    {
+        let mut hits = ScanHits::default();
+        let mut flushed = 0;
+        for pos in range {
+            if pos % CANCEL_CHECK_INTERVAL == 0 && cancel.is_cancelled() {
+                break;
+            }
+            let i = subset.map_or(pos, |s| s[pos]);
+            if i >= self.len() || self.removed[i] || !query.may_match(self.name_masks[i], self.path_masks[i]) {
+                continue;
+            }
+            let path = &self.paths[i];
+            let folded = self.folded(i);
+            if !accept(path, folded.path) {
+                continue;
+            }
+
+            // 多词 AND 匹配 + 别名/缩写 + 模糊兜底 (规则见 Query::evaluate)
+            let m = query.evaluate_folded(self.name(i), path, Some(&folded));
+            if m.is_full() {
+                hits.full.push(i);
+                if hits.full.len() - flushed >= STREAM_BATCH_SIZE {
+                    on_full(&hits.full[flushed..]);
+                    flushed = hits.full.len();
+                }
+            } else if m.fuzzy.is_some() {
+                if hits.fuzzy.len() < MAX_FUZZY {
+                    hits.fuzzy.push(i);
+                }
+            } else if m.matched_terms > 0 && query.terms.len() > 1 {
+                // 记录部分匹配的结果，作为 fallback
+                if hits.partial.len() < MAX_PARTIAL {
+                    hits.partial.push(i);
+                }
+            } else {
+                continue;
+            }
+            if hits.candidates.len() < MAX_CANDIDATES {
+                hits.candidates.push(i);
+            } else {
+                hits.candidates_truncated = true;
+            }
+        }
+        if flushed < hits.full.len() {
+            on_full(&hits.full[flushed..]);
+        }
+        hits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn index(paths: &[&str]) -> FileIndex {
+        FileIndex::new(paths.iter().map(|p| p.to_string()).collect(), usize::MAX)
+    }
+
+    fn scan(index: &FileIndex, keyword: &str) -> ScanHits {
+        let query = Query::new(keyword, SearchOptions::default(), &HashMap::new());
+        index.scan(&query, None, &CancelToken::default(), |_, _| true, |_| {})
+    }
+
+    #[test]
+    fn keys_match_folding_whole_path() {
+        let options = SearchOptions::default();
+        let idx = index(&["/Users/Me/簡歷/ＲＥＰＯＲＴ.PDF", "Re\u{301}sume\u{301}.txt", "/a/b／c.txt"]);
+        for i in 0..idx.len() {
+            assert_eq!(idx.key(i), options.fold_text(idx.path(i), true));
+            assert_eq!(idx.folded(i).name, options.fold_text(idx.name(i), true));
+        }
+    }
+
+    #[test]
+    fn scan_agrees_with_evaluate() {
+        let idx = index(&[
+            "/Users/me/Documents/Report 2024.pdf",
+            "/Users/me/Desktop/photo.jpg",
+            "/Applications/Adobe Photoshop 2024.app",
+            "/Users/me/report/notes.txt",
+        ]);
+        let hits = scan(&idx, "report");
+        assert_eq!(hits.full, vec![0, 3]);
+        let hits = scan(&idx, "phtshp");
+        assert_eq!(hits.fuzzy, vec![2]);
+        assert_eq!(hits.candidates, vec![2]);
+        assert!(!hits.candidates_truncated);
+    }
+
+    #[test]
+    fn scan_caps_partial_and_candidates() {
+        let paths: Vec<String> = (0..MAX_CANDIDATES + 10).map(|i| format!("/d/report {}.txt", i)).collect();
+        let idx = FileIndex::new(paths, usize::MAX);
+        // 严格命中全部保留，候选集超限后整体放弃
+        let hits = scan(&idx, "report");
+        assert_eq!(hits.full.len(), MAX_CANDIDATES + 10);
+        assert!(hits.candidates.is_empty() && hits.candidates_truncated);
+        // 部分匹配按上限截断
+        let hits = scan(&idx, "report zzqx");
+        assert!(hits.full.is_empty() && hits.partial.len() == MAX_PARTIAL);
+        assert!(hits.candidates_truncated);
+    }
+
+    #[test]
+    fn incremental_update_keeps_trigrams_in_sync() {
+        let mut paths: Vec<String> = (0..8).map(|i| format!("/misc/file{}.txt", i)).collect();
+        paths.extend(["/a/report.txt".to_string(), "/b/notes.txt".to_string()]);
+        let mut idx = FileIndex::new(paths.clone(), 0);
+        paths[9] = "/d/report final.pdf".to_string();
+        let update = idx.plan_update(paths);
+        assert!(matches!(&update, IndexUpdate::Changes { removed, added } if removed == &[9] && added.len() == 1));
+        idx.apply_update(update);
+        assert_eq!(idx.count(), 10);
+
+        let query = Query::new("report", SearchOptions::default(), &HashMap::new());
+        assert_eq!(idx.shortlist(&query), Some(vec![8, 10]));
+        assert_eq!(scan(&idx, "report").full, vec![8, 10]);
+        assert!(scan(&idx, "notes").full.is_empty());
+
+        // 缓存往返后下标与三元组索引保持一致
+        let cache = std::env::temp_dir().join(format!("xtap-index-test-{}.cache", std::process::id()));
+        let cipher = Cipher::new(Cipher::generate_key());
+        idx.save(&cache, &cipher).unwrap();
+        assert!(crate::crypto::is_encrypted(&cache) && crate::crypto::is_encrypted(&trigram_path(&cache)));
+        let loaded = FileIndex::load(&cache, &cipher, 0).unwrap();
+        assert!(FileIndex::load(&cache, &Cipher::new(Cipher::generate_key()), 0).is_err());
+        let _ = std::fs::remove_file(&cache);
+        let _ = std::fs::remove_file(trigram_path(&cache));
+        assert_eq!(loaded.count(), 10);
+        assert_eq!(loaded.shortlist(&query), Some(vec![8, 10]));
+    }
+
+    #[test]
+    fn narrowed_scan_matches_full_scan() {
+        let idx = index(&["/t/abcd.txt", "/t/xabcd.txt", "/t/xabce.txt", "/t/abcde notes.txt", "/t/abcdf.txt", "/t/report 2024.pdf"]);
+        let kinds = |hits: ScanHits| (hits.full, hits.fuzzy, hits.partial);
+        let mut refined = 0;
+        for (prev, next) in [("abcd", "xabcd"), ("abcd", "abcde"), ("abcd", "abcd notes"), ("report", "report 2024")] {
+            let (prev, next) = (Query::new(prev, SearchOptions::default(), &HashMap::new()), Query::new(next, SearchOptions::default(), &HashMap::new()));
+            let cancel = CancelToken::default();
+            let candidates = idx.scan(&prev, None, &cancel, |_, _| true, |_| {}).candidates;
+            let narrowed = idx.scan(&next, Some(&candidates), &cancel, |_, _| true, |_| {});
+            let full = idx.scan(&next, None, &cancel, |_, _| true, |_| {});
+            // 只有细化时才复用候选集，此时结果须与全量扫描一致
+            if next.refines(&prev) {
+                assert_eq!(kinds(narrowed), kinds(full));
+                refined += 1;
+            }
+        }
+        assert_eq!(refined, 1);
+        // 在前面加字符时两者确实不同，不能算细化
+        let (prev, next) = (Query::new("abcd", SearchOptions::default(), &HashMap::new()), Query::new("xabcd", SearchOptions::default(), &HashMap::new()));
+        let candidates = idx.scan(&prev, None, &CancelToken::default(), |_, _| true, |_| {}).candidates;
+        let narrowed = idx.scan(&next, Some(&candidates), &CancelToken::default(), |_, _| true, |_| {});
+        assert_ne!(kinds(narrowed), kinds(scan(&idx, "xabcd")));
+        assert!(!next.refines(&prev));
+    }
+
+    #[test]
+    fn subset_limits_scan() {
+        let idx = index(&["/a/report.txt", "/b/report.txt", "/c/report.txt"]);
+        let query = Query::new("report", SearchOptions::default(), &HashMap::new());
+        let hits = idx.scan(&query, Some(&[0, 2]), &CancelToken::default(), |_, _| true, |_| {});
+        assert_eq!(hits.full, vec![0, 2]);
+    }
+
+    // 合成索引 (500 万条) 上的扫描耗时，只打印供对比。50ms 的目标尚未达到，这里不检查耗时
+    // (实测数据见 README)，只断言与机器无关的部分：模糊、部分匹配与候选集都不超过上限。
+    // 运行: cargo test --release -- --ignored --nocapture scan_benchmark
+    #[test]
+    #[ignore]
+    fn scan_benchmark() {
+        const ENTRIES: usize = 5_000_000;
+        let words = ["project", "photo", "invoice", "backup", "notes", "draft", "archive", "music", "video", "report"];
+        let exts = ["pdf", "jpg", "docx", "txt", "rs", "mp4", "png", "xlsx"];
+        let paths: Vec<String> = (0..ENTRIES)
+            .map(|i| {
+                let w = |k: usize| words[(i / k) % words.len()];
+                format!("/Users/me/Documents/{}_{}/{}/{} {} {}.{}", w(1), i % 97, w(7), w(13), w(3), i, exts[i % exts.len()])
+            })
+            .collect();
+
+        let start = std::time::Instant::now();
+        let idx = FileIndex::new(paths, TRIGRAM_MIN_ENTRIES);
+        println!("构建 {} 条索引: {:?}", idx.len(), start.elapsed());
+
+        for keyword in ["invoice 2024", "zzqx", "phtshp", "reprot", "archive music"] {
+            let start = std::time::Instant::now();
+            let hits = scan(&idx, keyword);
+            println!("{:<14} 严格 {:>8} 模糊 {:>5} 部分 {:>8}  耗时 {:?}", keyword, hits.full.len(), hits.fuzzy.len(), hits.partial.len(), start.elapsed());
+            assert!(hits.fuzzy.len() <= MAX_FUZZY && hits.partial.len() <= MAX_PARTIAL && hits.candidates.len() <= MAX_CANDIDATES);
+
+            // 三元组索引圈定候选 + 复核
+            let start = std::time::Instant::now();
+            let query = Query::new(keyword, SearchOptions::default(), &HashMap::new());
+            if let Some(list) = idx.shortlist(&query) {
+                let hits = idx.scan(&query, Some(&list), &CancelToken::default(), |_, _| true, |_| {});
+                println!("{:<14} 三元组候选 {:>8} 严格 {:>8}  耗时 {:?}", keyword, list.len(), hits.full.len(), start.elapsed());
+            }
+        }
+    }
+}
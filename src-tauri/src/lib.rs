@@ -1,14 +1,41 @@
 use serde::Serialize;
 use std::process::Command;
-use std::sync::{Arc, Mutex};
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::collections::HashMap;
-  use tauri::{State, AppHandle, Manager};
+use std::borrow::Cow;
+  use tauri::{State, Manager};
   use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, Modifiers, Code};
   use tauri_plugin_cli::CliExt;
   use tokio::time::{sleep, Duration};
 use tokio::process::Command as AsyncCommand;
 
+mod aliases;
+mod apps;
+mod chinese;
+mod crypto;
+mod history;
+mod index;
+mod matcher;
+mod paths;
+mod privacy;
+mod query;
+mod ranking;
+mod session;
+mod sort;
+mod store;
+mod trigram;
+use aliases::{AliasConflict, AliasEntry, AliasTable, UserAliases};
+use crypto::Cipher;
+use history::{ClickHistory, HistoryOp, HistoryWriter};
+use privacy::PathPatterns;
+use query::{Highlight, Query, SearchOptions};
+use ranking::{RankingConfig, RankingProfile, ScoreExplanation};
+use session::{CancelToken, ScanCache, SearchSessions};
+use index::{FileIndex, STREAM_BATCH_SIZE};
+use sort::{SortDirection, SortKey, SortOrder};
+use store::{IndexRoot, SavedSearch, Store};
+
 #[derive(Serialize, Clone)]
  struct SearchResult {
     path: String,
@@ -16,24 +43,41 @@ use tokio::process::Command as AsyncCommand;
     // 内部字段，用于排序优化
     #[serde(skip)]
     score: i32,
+    // 命中方式与高亮区间，只为返回给前端的结果计算
+    #[serde(skip_serializing_if = "Option::is_none")]
+    highlight: Option<Highlight>,
+    // 得分明细，只在请求 explain 时计算
+    #[serde(skip_serializing_if = "Option::is_none")]
+    explain: Option<ScoreExplanation>,
 }
 
 // 全局索引状态
 #[derive(Clone)]
 struct GlobalIndex {
-    files: Arc<Mutex<Vec<String>>>,
+    files: Arc<RwLock<FileIndex>>,
     is_indexing: Arc<Mutex<bool>>,
     force_update: Arc<AtomicBool>,
+    // 索引版本号，每次替换文件列表时递增，缓存的候选下标随之失效
+    generation: Arc<AtomicU64>,
+    cipher: Arc<Cipher>, // 索引缓存文件的加密密钥
 }
 
-use std::fs::File;
-use std::io::{BufRead, BufReader, Write};
 use std::path::PathBuf;
 
+// 点击历史中的路径是否仍然有效；外接盘未挂载时其上的路径暂时看不到，不算消失
+fn history_path_alive(path: &str) -> bool {
+    if std::path::Path::new(path).exists() {
+        return true;
+    }
+    match path.strip_prefix("/Volumes/").and_then(|rest| rest.split('/').next()) {
+        Some(volume) => !std::path::Path::new("/Volumes").join(volume).exists(),
+        None => false,
+    }
+}
+
 // 获取索引文件路径
 fn get_index_path() -> PathBuf {
-    let home = std::env::var("HOME").unwrap_or_else(|_| "/".to_string());
-    let path = PathBuf::from(home).join("Library/Caches/com.xtap.search/index.cache");
+    let path = paths::app_dirs().index_path();
     if let Some(parent) = path.parent() {
         let _ = std::fs::create_dir_all(parent);
     }
@@ -41,33 +85,41 @@ fn get_index_path() -> PathBuf {
 }
 
 impl GlobalIndex {
-    fn new() -> Self {
-        let files = Arc::new(Mutex::new(Vec::new()));
+    fn new(cipher: Arc<Cipher>) -> Self {
+        let files = Arc::new(RwLock::new(FileIndex::default()));
         let is_indexing = Arc::new(Mutex::new(false));
         let force_update = Arc::new(AtomicBool::new(false));
         
         // 尝试加载现有索引
         let index_path = get_index_path();
         if index_path.exists() {
-            if let Ok(file) = File::open(&index_path) {
-                let reader = BufReader::new(file);
-                let mut loaded_files = Vec::new();
-                for line in reader.lines().map_while(Result::ok) {
-                    loaded_files.push(line);
+            match FileIndex::load(&index_path, &cipher, index::trigram_threshold()) {
+                Ok(loaded) => {
+                    println!("从缓存加载了 {} 条索引", loaded.count());
+                    // 旧版本的明文缓存立即加密重写
+                    if !crypto::is_encrypted(&index_path) {
+                        if let Err(e) = loaded.save(&index_path, &cipher) {
+                            println!("加密索引缓存失败: {}", e);
+                        }
+                    }
+                    let mut guard = files.write().unwrap();
+                    *guard = loaded;
                 }
-                println!("从缓存加载了 {} 条索引", loaded_files.len());
-                let mut guard = files.lock().unwrap();
-                *guard = loaded_files;
+                // 索引可以重新扫描生成，解不开 (如密钥丢失) 时直接丢弃
+                Err(e) => println!("无法读取索引缓存 ({})，将重新扫描", e),
             }
         }
 
-        Self { files, is_indexing, force_update }
+        Self { files, is_indexing, force_update, generation: Arc::new(AtomicU64::new(0)), cipher }
     }
 
-    fn start_indexing(&self) {
+    /// 启动后台索引任务；每次重新扫描后把扫描到的全部路径及各根目录的扫描情况交给 `on_scan`
+    fn start_indexing(&self, on_scan: impl Fn(&[String], &[IndexRoot]) + Send + 'static) {
         let files_clone = self.files.clone();
         let status_clone = self.is_indexing.clone();
         let force_update_clone = self.force_update.clone();
+        let generation_clone = self.generation.clone();
+        let cipher = self.cipher.clone();
         tauri::async_runtime::spawn(async move {
             let mut last_volumes = std::collections::HashSet::new();
             let mut last_full_scan = std::time::Instant::now();
@@ -99,22 +151,22 @@ impl GlobalIndex {
                     }
                     
                     // 扫描路径：本地常用 + 外接盘 + 应用程序
-                    let home = std::env::var("HOME").unwrap_or_else(|_| "/".to_string());
-                    let mut scan_paths = vec![
-                        format!("{}/Desktop", home),
-                        format!("{}/Downloads", home),
-                        format!("{}/Documents", home),
-                        "/Applications".to_string(),
-                    ];
+                    // 取不到用户目录时只扫描应用程序与外接盘
+                    let mut scan_paths: Vec<String> = dirs::home_dir()
+                        .map(|home| ["Desktop", "Downloads", "Documents"].map(|d| home.join(d).to_string_lossy().into_owned()).to_vec())
+                        .unwrap_or_default();
+                    scan_paths.push("/Applications".to_string());
                     
                     if std::path::Path::new("/Volumes").exists() {
                         scan_paths.push("/Volumes".to_string());
                     }
 
                     let mut all_files = Vec::new();
+                    let mut roots = Vec::new();
                     for path in scan_paths {
                         if !std::path::Path::new(&path).exists() { continue; }
                         println!("正在扫描路径: {} ...", path);
+                        let started = std::time::Instant::now();
                         
                         let output = AsyncCommand::new("find")
                             .arg(&path)
@@ -133,21 +185,30 @@ impl GlobalIndex {
                                 }
                             }
                             println!("路径 {} 扫描完成，找到 {} 个文件", path, count);
+                            roots.push(IndexRoot {
+                                root: path.clone(),
+                                entries: count,
+                                scanned_at: history::unix_now(),
+                                scan_ms: started.elapsed().as_millis() as u64,
+                            });
                         }
                     }
                     
-                    // 保存到缓存文件
-                    let index_path = get_index_path();
-                    if let Ok(mut file) = File::create(&index_path) {
-                        for f in &all_files {
-                            let _ = writeln!(file, "{}", f);
-                        }
-                    }
+                    on_scan(&all_files, &roots);
+
+                    // 与现有索引比对：变化不大时增量更新 (三元组索引随之维护)，否则重建。
+                    // 比对与重建只需读锁，不阻塞进行中的搜索
+                    let update = files_clone.read().unwrap().plan_update(all_files);
+                    let count = {
+                        let mut guard = files_clone.write().unwrap();
+                        guard.apply_update(update);
+                        generation_clone.fetch_add(1, Ordering::Relaxed);
+                        guard.count()
+                    };
 
-                    let count = all_files.len();
-                    {
-                        let mut guard = files_clone.lock().unwrap();
-                        *guard = all_files;
+                    // 保存到缓存文件
+                    if let Err(e) = files_clone.read().unwrap().save(&get_index_path(), &cipher) {
+                        println!("保存索引缓存失败: {}", e);
                     }
                     {
                         let mut guard = status_clone.lock().unwrap();
@@ -168,124 +229,234 @@ fn get_indexing_status(state: State<'_, AppCache>) -> bool {
     *state.index.is_indexing.lock().unwrap()
 }
 
+// 保存在数据库 settings 表中的隐私设置
+const PRIVATE_MODE_SETTING: &str = "privateMode";
+const PRIVACY_EXCLUSIONS_SETTING: &str = "privacyExclusions";
+
 // 应用缓存
 #[derive(Clone)]
 struct AppCache {
-    mapping: Arc<Mutex<HashMap<String, String>>>,
-    click_history: Arc<Mutex<HashMap<String, u32>>>, // 新增：点击历史记录 (路径 -> 点击次数)
+    aliases: Arc<RwLock<AliasTable>>, // 别名表：内置 + 应用名 + aliases.json
+    click_history: Arc<Mutex<ClickHistory>>, // 点击历史记录 (路径 -> 各次打开时间)
+    history_writer: HistoryWriter, // 把点击历史的改动写入数据库的后台线程
+    store: Arc<Store>, // 内嵌数据库：点击历史、设置、保存的搜索、自定义别名、索引信息
+    cipher: Arc<Cipher>, // 数据库与索引缓存共用的存储密钥
+    private_mode: Arc<AtomicBool>, // 无痕模式：打开文件时不记录历史
+    privacy_exclusions: Arc<RwLock<PathPatterns>>, // 从不记录的路径
     index: GlobalIndex,
+    sessions: SearchSessions, // 每个窗口的搜索会话，用于取消过期查询
+    ranking: Arc<RwLock<RankingConfig>>, // 排序方案，来自 ranking.json
 }
 
 impl AppCache {
     fn new() -> Self {
+        // 先把旧版本写在其它位置的文件迁移过来，再加载索引与数据库
+        let dirs = paths::app_dirs();
+        let migrated = paths::migrate(&dirs.migrations(dirs::home_dir().as_deref(), &paths::AppDirs::standard()));
+        if migrated > 0 {
+            println!("已迁移 {} 个旧版本数据文件", migrated);
+        }
+        // 索引缓存与数据库加密存放；取不到密钥时数据库只使用内存，不碰磁盘上的旧数据库
+        let (cipher, store) = match crypto::load_or_create_key(dirs) {
+            Ok(key) => {
+                let cipher = Cipher::new(key);
+                let store = Store::open(&dirs.store_path(), &cipher.sqlcipher_key()).unwrap_or_else(|e| {
+                    eprintln!("{}，本次运行的改动不会保存", e);
+                    Store::in_memory()
+                });
+                (cipher, store)
+            }
+            Err(e) => {
+                eprintln!("{}，本次运行的改动不会保存", e);
+                (Cipher::new(Cipher::generate_key()), Store::in_memory())
+            }
+        };
+        let cipher = Arc::new(cipher);
+        let store = Arc::new(store);
+        let click_history = Arc::new(Mutex::new(Self::load_click_history(&store)));
+        let writer_store = store.clone();
         let cache = Self {
-            mapping: Arc::new(Mutex::new(HashMap::new())),
-            click_history: Arc::new(Mutex::new(HashMap::new())),
-            index: GlobalIndex::new(),
+            aliases: Arc::new(RwLock::new(AliasTable::default())),
+            history_writer: HistoryWriter::spawn(move |ops| writer_store.apply_history(ops)),
+            click_history,
+            store,
+            index: GlobalIndex::new(cipher.clone()),
+            cipher,
+            sessions: SearchSessions::default(),
+            ranking: Arc::new(RwLock::new(RankingConfig::default())),
+            private_mode: Arc::new(AtomicBool::new(false)),
+            privacy_exclusions: Arc::new(RwLock::new(PathPatterns::default())),
         };
-        cache.load_click_history(); // 启动时加载历史
+        if let Err(e) = cache.load_ranking_config() {
+            eprintln!("{}，使用内置排序方案", e);
+        }
+        cache.load_privacy_settings();
+        cache.migrate_store_aliases();
         cache.update();
         cache
     }
 
-    // 从磁盘加载点击历史
-    fn load_click_history(&self) {
-        if let Some(mut path) = dirs::cache_dir() {
-            path.push("com.xtap.search");
-            path.push("click_history.json");
-            if let Ok(content) = std::fs::read_to_string(path) {
-                if let Ok(history) = serde_json::from_str::<HashMap<String, u32>>(&content) {
-                    let mut mine = self.click_history.lock().unwrap();
-                    *mine = history;
-                    println!("从缓存加载了 {} 条点击历史", mine.len());
-                }
+    // 从数据库加载点击历史；数据库新建时先导入早期版本的 click_history.json
+    fn load_click_history(store: &Store) -> ClickHistory {
+        if store.created() {
+            match store.import_click_history(&paths::app_dirs().legacy_click_history_path()) {
+                Ok(0) => {}
+                Ok(n) => println!("已将 {} 次点击记录导入数据库", n),
+                Err(e) => eprintln!("导入点击历史失败: {}", e),
+            }
+        }
+        match store.load_history() {
+            Ok(history) => {
+                println!("从数据库加载了 {} 条点击历史", history.len());
+                history
+            }
+            Err(e) => {
+                eprintln!("加载点击历史失败: {}", e);
+                ClickHistory::default()
             }
         }
     }
 
-    // 保存点击历史到磁盘
-    fn save_click_history(&self) {
-        if let Some(mut path) = dirs::cache_dir() {
-            path.push("com.xtap.search");
-            let _ = std::fs::create_dir_all(&path);
-            path.push("click_history.json");
-            let mine = self.click_history.lock().unwrap();
-            if let Ok(content) = serde_json::to_string(&*mine) {
-                let _ = std::fs::write(path, content);
-            }
+    // 从配置目录加载排序方案，出错时保留当前方案
+    fn load_ranking_config(&self) -> Result<(), String> {
+        let home = dirs::home_dir().map(|h| h.to_string_lossy().into_owned());
+        let config = RankingConfig::load(&paths::app_dirs().ranking_config_path(), home.as_deref())?;
+        *self.ranking.write().unwrap() = config;
+        Ok(())
+    }
+
+    // 某个搜索类型当前使用的排序方案
+    fn ranking_profile(&self, filter_type: &str) -> Arc<RankingProfile> {
+        self.ranking.read().unwrap().profile_for(filter_type)
+    }
+
+    // 记录一次打开并保存；带上当时的查询时同时学习「这个查询选了这个结果」。
+    // 返回该路径的打开总次数，无痕模式或路径被排除时不记录，返回 None
+    fn record_open(&self, path: &str, query: Option<&str>) -> Option<u32> {
+        if self.private_mode.load(Ordering::Relaxed) || self.privacy_exclusions.read().unwrap().matches(path) {
+            return None;
+        }
+        let key = query.map(query::learning_key).filter(|k| !k.is_empty());
+        self.change_history(HistoryOp::open(path, key.as_deref(), history::unix_now()));
+        Some(self.click_history.lock().unwrap().total(path))
+    }
+
+    // 从数据库读取无痕模式与隐私排除规则，并清除已有记录中被排除的路径
+    fn load_privacy_settings(&self) {
+        let settings = self.store.settings().unwrap_or_default();
+        let private = settings.get(PRIVATE_MODE_SETTING).and_then(|v| v.as_bool()).unwrap_or(false);
+        self.private_mode.store(private, Ordering::Relaxed);
+        let patterns: Vec<String> = settings
+            .get(PRIVACY_EXCLUSIONS_SETTING)
+            .and_then(|v| serde_json::from_value(v.clone()).ok())
+            .unwrap_or_default();
+        if let Err(e) = self.set_privacy_exclusions(patterns) {
+            eprintln!("{}", e);
+        }
+    }
+
+    // 替换隐私排除规则，清除已有记录中被排除的路径，返回清除的路径数
+    fn set_privacy_exclusions(&self, patterns: Vec<String>) -> Result<usize, String> {
+        let home = dirs::home_dir().map(|h| h.to_string_lossy().into_owned());
+        let rules = PathPatterns::new(&patterns, home.as_deref())?;
+        *self.privacy_exclusions.write().unwrap() = rules;
+        Ok(self.purge_excluded_history())
+    }
+
+    // 删除被隐私规则排除的路径的全部记录 (含按查询学习的)，使其不再参与加分
+    fn purge_excluded_history(&self) -> usize {
+        let excluded: Vec<String> = {
+            let rules = self.privacy_exclusions.read().unwrap();
+            self.click_history.lock().unwrap().paths().into_iter().filter(|p| rules.matches(p)).collect()
+        };
+        for path in &excluded {
+            self.change_history(HistoryOp::Forget { path: path.clone() });
+        }
+        excluded.len()
+    }
+
+    // 清理点击历史：已不存在的路径能在扫描结果中唯一对上新位置的迁移过去，其余删除。
+    // 所在外接盘未挂载的路径视为仍然存在。
+    fn tidy_click_history(&self, scanned: &[String]) {
+        let paths = self.click_history.lock().unwrap().paths();
+        let missing: Vec<String> = paths.into_iter().filter(|p| !history_path_alive(p)).collect();
+        if missing.is_empty() {
+            return;
+        }
+        let moves = history::detect_moves(&missing, scanned);
+        for (from, to) in &moves {
+            self.change_history(HistoryOp::Rename { from: from.clone(), to: to.clone() });
+        }
+        for path in &missing {
+            self.change_history(HistoryOp::Forget { path: path.clone() });
         }
+        println!("清理点击历史: 迁移 {} 条, 删除 {} 条", moves.len(), missing.len() - moves.len());
     }
 
+    // 修改内存中的点击历史，并交给后台线程写入数据库，不阻塞调用方
+    fn change_history(&self, op: HistoryOp) {
+        let evicted = self.click_history.lock().unwrap().apply(&op);
+        self.history_writer.submit(op);
+        // 超出上限而淘汰的记录也要从数据库删除，否则数据库无限增长、重启后又被读回
+        if let Some(evicted) = evicted {
+            self.history_writer.submit(evicted);
+        }
+    }
+
+    // 每轮扫描后：清理点击历史，记录各根目录的扫描情况
+    fn after_scan(&self, scanned: &[String], roots: &[IndexRoot]) {
+        self.tidy_click_history(scanned);
+        // 迁移后的新位置可能落在排除规则内
+        self.purge_excluded_history();
+        if let Err(e) = self.store.record_index_roots(roots) {
+            eprintln!("记录索引信息失败: {}", e);
+        }
+    }
+
+    // 重建别名表：内置别名、已安装应用的名称与本地化显示名，再叠加用户的 aliases.json
     fn update(&self) {
-        let mut new_map = HashMap::new();
-        // 1. 核心工业级映射表 - 覆盖设计、社交、工具、办公等常用软件
-        // 支持拼音缩写、中文俗称、英文原名
-        let aliases = [
-            // 设计类
-            ("ps", "photoshop"), ("lr", "lightroom"), ("pr", "premiere"), ("ae", "after effects"), ("ai", "illustrator"),
-            ("id", "indesign"), ("au", "audition"), ("dw", "dreamweaver"), ("an", "animate"), ("pl", "prelude"),
-            ("br", "bridge"), ("ch", "character animator"), ("me", "media encoder"), ("ic", "incopy"), ("fs", "fuse"),
-            ("sc", "scout"), ("st", "stock"), ("xd", "xd"), ("dc", "acrobat"), ("dpp", "digital photo professional"),
-            ("fcpx", "final cut pro"), ("c4d", "cinema 4d"), ("sketch", "sketch"), ("figma", "figma"),
-            ("photoshop", "photoshop"), ("illustrator", "illustrator"), ("premiere", "premiere"), 
-            ("aftereffects", "after effects"), ("lightroom", "lightroom"),
-            // 社交/办公
-            ("wx", "wechat"), ("微信", "wechat"), ("qq", "qq"), ("dd", "dingtalk"), ("钉钉", "dingtalk"),
-            ("fs", "feishu"), ("飞书", "feishu"), ("lark", "feishu"), ("word", "microsoft word"), ("excel", "microsoft excel"),
-            ("ppt", "microsoft powerpoint"), ("wps", "wpsoffice"), ("pdf", "acrobat"), ("obs", "obs studio"),
-            ("yx", "neteasemail"), ("邮箱", "mail"), ("notes", "notes"), ("memo", "notes"), ("wechat", "wechat"),
-            ("dingtalk", "dingtalk"), ("feishu", "feishu"),
-            // 视频/娱乐/AI
-            ("jy", "videofusion"), ("剪映", "videofusion"), ("capcut", "videofusion"), ("vf", "videofusion"),
-            ("db", "doubao"), ("豆包", "doubao"), ("doubao", "doubao"), ("videofusion", "videofusion"),
-            ("db", "douban"), ("dy", "douyin"), ("bili", "bilibili"), ("bz", "bilibili"), ("music", "music"), 
-            ("网易云", "neteasemusic"), ("spotify", "spotify"), ("douyin", "douyin"), ("tiktok", "douyin"),
-            ("jianying", "videofusion"), ("jianyingpro", "videofusion"),
-            // 生产力
-            ("wp", "wpsoffice"), ("wps", "wpsoffice"), ("word", "microsoft word"), ("excel", "microsoft excel"),
-            ("ppt", "microsoft powerpoint"), ("pages", "pages"), ("numbers", "numbers"), ("keynote", "keynote"),
-            // 工具/开发
-            ("llq", "browser"), ("浏览器", "browser"), ("safari", "safari"), ("chrome", "google chrome"), 
-            ("edge", "microsoft edge"), ("fd", "finder"), ("访达", "finder"), ("zd", "terminal"), ("终端", "terminal"),
-            ("iterm", "iterm"), ("code", "visual studio code"), ("vs", "visual studio code"), ("vscode", "visual studio code"),
-            ("st", "sublime text"), ("idea", "intellij idea"), ("webstorm", "webstorm"), ("py", "pycharm"),
-            ("git", "github"), ("postman", "postman"), ("docker", "docker"),
-            // 系统/其他
-            ("sz", "settings"), ("设置", "settings"), ("jh", "calculator"), ("计算器", "calculator"),
-            ("activity", "activity monitor"), ("monitor", "activity monitor"), ("disk", "disk utility"),
-            ("keychain", "keychain access"), ("console", "console"),
-        ];
-        
-        for (alias, real) in aliases {
-            new_map.insert(alias.to_string(), real.to_string());
+        let path = paths::app_dirs().alias_config_path();
+        let user = UserAliases::load(&path).unwrap_or_else(|e| {
+            eprintln!("{}，只使用内置别名", e);
+            UserAliases::default()
+        });
+        let apps = aliases::app_aliases(&apps::installed_apps());
+        let table = AliasTable::build(aliases::BUILTIN_ALIASES, &apps, &user);
+        for conflict in &table.conflicts {
+            let ignored: Vec<String> = conflict.ignored.iter().map(|e| e.targets.join(" / ")).collect();
+            println!("别名 {} 有多个定义，使用 {}，忽略 {}", conflict.key, conflict.used.targets.join(" / "), ignored.join(", "));
         }
+        *self.aliases.write().unwrap() = table;
+    }
 
-        // 2. 动态扫描 /Applications 以补充映射 (处理带中文名的 App)
-        if let Ok(entries) = std::fs::read_dir("/Applications") {
-            for entry in entries.flatten() {
-                let name = entry.file_name().to_string_lossy().to_string();
-                if name.ends_with(".app") {
-                    let base_name = name.replace(".app", "").to_lowercase();
-                    // 记录全名
-                    new_map.entry(base_name.clone()).or_insert(base_name.clone());
-                    
-                    // 如果名字包含空格或特殊字符，建立简写映射
-                    if base_name.contains(' ') || base_name.contains('-') {
-                        let short: String = base_name
-                            .split([' ', '-'])
-                            .filter(|s| !s.is_empty())
-                            .map(|s| s.chars().next().unwrap_or(' '))
-                            .collect();
-                        if short.len() > 1 {
-                            new_map.entry(short).or_insert(base_name.clone());
-                        }
-                    }
-                }
+    // 早期版本保存在数据库中的自定义别名移入 aliases.json (已有同名定义的不覆盖)
+    fn migrate_store_aliases(&self) {
+        let legacy = match self.store.take_aliases() {
+            Ok(legacy) if !legacy.is_empty() => legacy,
+            Ok(_) => return,
+            Err(e) => return eprintln!("读取旧的自定义别名失败: {}", e),
+        };
+        let path = paths::app_dirs().alias_config_path();
+        let result = UserAliases::load(&path).and_then(|mut user| {
+            for (alias, target) in &legacy {
+                user.add_missing(alias, target);
             }
+            user.save(&path)
+        });
+        match result {
+            Ok(()) => println!("已将 {} 条自定义别名移入 {}", legacy.len(), path.display()),
+            Err(e) => eprintln!("{}", e),
         }
+    }
 
-        let mut guard = self.mapping.lock().unwrap();
-        *guard = new_map;
+    // 修改 aliases.json 并立即重建别名表
+    fn edit_user_aliases(&self, edit: impl FnOnce(&mut UserAliases)) -> Result<(), String> {
+        let path = paths::app_dirs().alias_config_path();
+        let mut user = UserAliases::load(&path)?;
+        edit(&mut user);
+        user.save(&path)?;
+        self.update();
+        Ok(())
     }
 }
 
@@ -333,29 +504,34 @@ impl SearchStrategy {
         }
     }
 
-    fn spotlight_query(&self, words: &[&str], alias: Option<&String>) -> String {
+    fn spotlight_query(&self, query: &Query) -> String {
+        // c: 忽略大小写, d: 忽略变音符号
+        let flags = if query.options.case_sensitive { "d" } else { "cd" };
         let mut parts = Vec::new();
-        for word in words {
-            if !word.is_empty() {
-                parts.push(format!("kMDItemFSName == '*{}*'cd", word));
+        for (word, targets) in query.terms.iter().zip(&query.term_aliases) {
+            let mut alternatives = vec![format!("kMDItemFSName == '*{}*'{}", word, flags)];
+            // 简繁等价：同时查询繁体写法
+            let traditional = if query.options.chinese_variants { chinese::to_traditional(word) } else { Cow::Borrowed(word.as_str()) };
+            if traditional != word.as_str() {
+                alternatives.push(format!("kMDItemFSName == '*{}*'{}", traditional, flags));
+            }
+            // 别名的每个目标都是该词的备选，其余词仍是 AND 条件；先后顺序由排序决定
+            alternatives.extend(targets.iter().map(|t| format!("kMDItemFSName == '*{}*'cd", t)));
+            if alternatives.len() > 1 {
+                parts.push(format!("({})", alternatives.join(" || ")));
+            } else {
+                parts.append(&mut alternatives);
             }
         }
 
-        if parts.is_empty() && alias.is_none() {
+        if parts.is_empty() {
             return self.spotlight_kind.clone();
         }
 
-        let base_query = if let Some(en_name) = alias {
-            let alias_part = format!("kMDItemFSName == '*{}*'cd", en_name);
-            if !parts.is_empty() {
-                format!("(({}) || {})", parts.join(" && "), alias_part)
-            } else {
-                alias_part
-            }
-        } else if parts.len() > 1 {
+        let base_query = if parts.len() > 1 {
             format!("({})", parts.join(" && "))
         } else {
-            parts[0].clone()
+            parts.remove(0)
         };
 
         if self.spotlight_kind.is_empty() {
@@ -365,9 +541,9 @@ impl SearchStrategy {
         }
     }
 
-    fn matches_extension(&self, path: &str) -> bool {
+    /// `path_lc` 须已转小写 (如内存索引中的比较键)
+    fn matches_extension(&self, path_lc: &str) -> bool {
         if self.extensions.is_empty() { return true; }
-        let path_lc = path.to_lowercase();
         // 针对 App 的特殊处理：只要路径中包含 .app 且不在 Contents 内部，就认为是程序
         if self.extensions.contains(&".app")
             && path_lc.contains(".app") && !path_lc.contains(".app/contents/") {
@@ -377,44 +553,253 @@ impl SearchStrategy {
     }
 }
 
+/// 默认每页条数 (与旧版固定截断的 100 条一致)
+const DEFAULT_PAGE_SIZE: usize = 100;
+
+/// 分页搜索结果
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SearchPage {
+    results: Vec<SearchResult>,
+    /// 匹配总数 (不受分页影响)
+    total: usize,
+    offset: usize,
+    /// 查询序号，前端据此丢弃过期的响应
+    seq: u64,
+    /// 已被更新的查询取代，结果为空
+    cancelled: bool,
+    /// 为 false 时只查找了严格命中 (大索引的三元组快速路径)，缩写、部分匹配与模糊结果未列出，`total` 偏少
+    complete: bool,
+}
+
+/// 流式搜索推送的一批结果，`done` 为 true 时表示搜索结束
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct SearchBatch {
+    seq: u64,
+    results: Vec<SearchResult>,
+    /// 截至本批已推送的结果总数
+    total: usize,
+    done: bool,
+    /// 搜索已结束且结果完整，含义同 `SearchPage::complete`；只有 `done` 批次可能为 true
+    complete: bool,
+}
+
+/// 搜索过程中分批产出结果的通道
+type BatchSink = tokio::sync::mpsc::UnboundedSender<Vec<SearchResult>>;
+
+/// 一次搜索排序后的全部结果
+struct SearchOutput {
+    results: Vec<SearchResult>,
+    /// 含义同 `SearchPage::complete`
+    complete: bool,
+}
+
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 async fn search_files(
     keyword: String, 
     filter_type: String, 
+    options: Option<SearchOptions>,
+    offset: Option<usize>,
+    limit: Option<usize>,
+    seq: Option<u64>,
+    sort: Option<SortOrder>,
+    explain: Option<bool>,
     state: State<'_, AppCache>, 
-    _app: AppHandle
-) -> Result<Vec<SearchResult>, String> {
-    search_files_internal(keyword, filter_type, state.inner().clone()).await
+    window: tauri::WebviewWindow,
+) -> Result<SearchPage, String> {
+    let offset = offset.unwrap_or(0);
+    // 新查询会取消同一窗口中仍在进行的旧查询
+    let Some((seq, cancel)) = state.sessions.begin(window.label(), seq) else {
+        return Ok(SearchPage { results: Vec::new(), total: 0, offset, seq: seq.unwrap_or(0), cancelled: true, complete: false });
+    };
+
+    let options = options.unwrap_or_default();
+    let query = {
+        let aliases = state.aliases.read().unwrap();
+        Query::new(&keyword, options.clone(), &aliases.mapping)
+    };
+    let session = Some((window.label().to_string(), seq));
+    let output = search_files_internal(keyword, filter_type.clone(), options, state.inner().clone(), None, cancel.clone(), session).await?;
+    if cancel.is_cancelled() {
+        return Ok(SearchPage { results: Vec::new(), total: 0, offset, seq, cancelled: true, complete: false });
+    }
+    let history = state.click_history.lock().unwrap().clone();
+    let results = sort::sort_results(output.results, sort.unwrap_or_default(), &history);
+    let (results, total) = page_of(results, offset, limit);
+    // 高亮与得分明细只为当前页计算
+    let explain = explain.unwrap_or(false);
+    let profile = state.ranking_profile(&filter_type);
+    let results = results
+        .into_iter()
+        .map(|mut res| {
+            res.highlight = Some(query.highlight(&res.name, &res.path));
+            if explain {
+                res.explain = Some(ranking::explain(&res, &query, &filter_type, &history, &profile));
+            }
+            res
+        })
+        .collect();
+    Ok(SearchPage { results, total, offset, seq, cancelled: false, complete: output.complete })
+}
+
+/// 取出排好序的结果中的一页，同时返回匹配总数；未指定 `limit` 时每页 `DEFAULT_PAGE_SIZE` 条
+fn page_of(results: Vec<SearchResult>, offset: usize, limit: Option<usize>) -> (Vec<SearchResult>, usize) {
+    let total = results.len();
+    let page = results.into_iter().skip(offset).take(limit.unwrap_or(DEFAULT_PAGE_SIZE)).collect();
+    (page, total)
+}
+
+/// 流式搜索：边找边通过 Channel 推送结果，不受分页上限限制
+#[tauri::command]
+async fn search_files_stream(
+    keyword: String,
+    filter_type: String,
+    options: Option<SearchOptions>,
+    seq: Option<u64>,
+    on_batch: tauri::ipc::Channel<SearchBatch>,
+    state: State<'_, AppCache>,
+    window: tauri::WebviewWindow,
+) -> Result<usize, String> {
+    let Some((seq, cancel)) = state.sessions.begin(window.label(), seq) else {
+        return Ok(0);
+    };
+    let state = state.inner().clone();
+    let session = Some((window.label().to_string(), seq));
+    stream_search(keyword, filter_type, options.unwrap_or_default(), state, seq, cancel, session, move |batch| {
+        let _ = on_batch.send(batch);
+    }).await
 }
 
+/// 运行一次搜索并把结果分批交给 `emit`，最后一批 `done = true`，返回结果总数。GUI 与 CLI 共用。
+/// 被取消后不再推送任何批次。
+#[allow(clippy::too_many_arguments)]
+async fn stream_search(
+    keyword: String,
+    filter_type: String,
+    options: SearchOptions,
+    state: AppCache,
+    seq: u64,
+    cancel: Arc<CancelToken>,
+    session: Option<(String, u64)>,
+    emit: impl Fn(SearchBatch) + Send + 'static,
+) -> Result<usize, String> {
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel::<Vec<SearchResult>>();
+    let search = tokio::spawn(search_files_internal(keyword, filter_type, options, state, Some(tx), cancel.clone(), session));
+    relay_batches(rx, async { search.await.map_err(|e| e.to_string())?.map(|output| output.complete) }, seq, &cancel, emit).await
+}
+
+// 把搜索过程中产出的批次依次交给 `emit`，`search` 结束后再发出 `done` 批次 (带上其返回的结果是否完整)；
+// 被取消后丢弃剩余批次
+async fn relay_batches(
+    mut rx: tokio::sync::mpsc::UnboundedReceiver<Vec<SearchResult>>,
+    search: impl std::future::Future<Output = Result<bool, String>>,
+    seq: u64,
+    cancel: &CancelToken,
+    emit: impl Fn(SearchBatch),
+) -> Result<usize, String> {
+    let mut total = 0;
+    while let Some(results) = rx.recv().await {
+        if cancel.is_cancelled() {
+            continue;
+        }
+        total += results.len();
+        emit(SearchBatch { seq, results, total, done: false, complete: false });
+    }
+
+    let complete = search.await?;
+    if !cancel.is_cancelled() {
+        emit(SearchBatch { seq, results: Vec::new(), total, done: true, complete });
+    }
+    Ok(total)
+}
+
+// 运行一次 mdfind，超时或被取消时杀掉子进程并返回空结果
+async fn run_mdfind(mut cmd: AsyncCommand, limit: Duration, cancel: &CancelToken) -> String {
+    cmd.kill_on_drop(true);
+    tokio::select! {
+        output = tokio::time::timeout(limit, cmd.output()) => match output {
+            Ok(Ok(o)) => String::from_utf8_lossy(&o.stdout).to_string(),
+            _ => String::new(),
+        },
+        _ = cancel.cancelled() => String::new(),
+    }
+}
+
+/// 结果不完整时 CLI 在末尾给出的提示
+const INCOMPLETE_NOTE: &str = "注意: 索引较大，只列出了严格命中的结果，缩写、部分匹配与模糊结果未查找，总数可能偏少";
+
+/// CLI 输出一条结果：默认为「名称 -> 路径」，`json` 时每行一个 JSON 对象 (JSON Lines)；
+/// 附带得分明细时文本模式在下方逐条缩进列出
+fn print_cli_result(res: &SearchResult, json: bool) {
+    if json {
+        if let Ok(line) = serde_json::to_string(res) {
+            println!("{}", line);
+        }
+        return;
+    }
+    println!("{} -> {}", res.name, res.path);
+    if let Some(explain) = res.explain.as_ref() {
+        println!("    得分 {}", explain.total);
+        for rule in &explain.rules {
+            println!("    {:+7}  {}", rule.points, rule.rule);
+        }
+    }
+}
+
+// 排序并推送一批结果 (仅流式模式)
+fn flush_batch(sink: Option<&BatchSink>, batch: &mut Vec<SearchResult>, query: &Query, filter_type: &str, history: &ClickHistory, profile: &RankingProfile) {
+    let Some(sink) = sink else { return };
+    if batch.is_empty() {
+        return;
+    }
+    for res in batch.iter_mut() {
+        ranking::rank_result(res, query, filter_type, history, profile);
+        res.highlight = Some(query.highlight(&res.name, &res.path));
+    }
+    batch.sort_by(|a, b| b.score.cmp(&a.score));
+    let _ = sink.send(std::mem::take(batch));
+}
+
+/// 执行搜索并返回全部排序后的结果及其是否完整；传入 `sink` 时边搜索边分批推送。
+/// `cancel` 被触发后尽快结束 (杀掉 mdfind、中断内存扫描)，此时返回的结果不完整，调用方应丢弃。
+/// `session` 为 (窗口 label, 查询序号)，用于复用同一窗口上一次查询的候选集；CLI 传 None。
+#[allow(clippy::too_many_arguments)]
 async fn search_files_internal(
     keyword: String, 
     filter_type: String, 
-    state: AppCache
-) -> Result<Vec<SearchResult>, String> {
+    options: SearchOptions,
+    state: AppCache,
+    sink: Option<BatchSink>,
+    cancel: Arc<CancelToken>,
+    session: Option<(String, u64)>,
+) -> Result<SearchOutput, String> {
     let start_time = std::time::Instant::now();
-    let keyword_lc = keyword.to_lowercase();
+    // 查询与索引统一做 NFC + 全角转半角 + 大小写折叠
+    let query = {
+        let aliases = state.aliases.read().unwrap();
+        Query::new(&keyword, options, &aliases.mapping)
+    };
     
-    if keyword_lc.trim().is_empty() {
-        return Ok(Vec::new());
+    if query.is_empty() {
+        return Ok(SearchOutput { results: Vec::new(), complete: true });
     }
 
     println!("收到极速搜索请求: keyword='{}', type='{}'", keyword, filter_type);
 
     // 1. 并行执行搜索任务
     let spotlight_handle = {
-        let keyword_lc = keyword_lc.clone();
         let filter_type_inner = filter_type.clone();
         let strategy = SearchStrategy::from_type(&filter_type_inner);
-        let mapping = state.mapping.lock().unwrap().clone();
-        let mapped_keyword = mapping.get(&keyword_lc).cloned();
+        let query = query.clone();
+        let cancel = cancel.clone();
         
         tokio::spawn(async move {
             let mut results = Vec::new();
             
             // 使用策略对象生成标准 Spotlight 查询
-            let words: Vec<&str> = keyword_lc.split_whitespace().collect();
-            let final_query = strategy.spotlight_query(&words, mapped_keyword.as_ref());
+            let final_query = strategy.spotlight_query(&query);
             
             println!("Spotlight 原始查询: {}", final_query);
 
@@ -422,36 +807,24 @@ async fn search_files_internal(
             
             // 任务 A: 用户目录 + 应用程序
             let q1 = final_query.clone();
+            let cancel_a = cancel.clone();
             tasks.push(tokio::spawn(async move {
-                let home = std::env::var("HOME").unwrap_or_else(|_| "/Users".to_string());
-                let output = tokio::time::timeout(
-                    std::time::Duration::from_secs(3),
-                    AsyncCommand::new("mdfind")
-                        .arg("-onlyin").arg(home)
-                        .arg("-onlyin").arg("/Applications")
-                        .arg(&q1)
-                        .output()
-                ).await;
-                match output {
-                    Ok(Ok(o)) => String::from_utf8_lossy(&o.stdout).to_string(),
-                    _ => String::new(),
-                }
+                let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("/Users"));
+                let mut cmd = AsyncCommand::new("mdfind");
+                cmd.arg("-onlyin").arg(home)
+                    .arg("-onlyin").arg("/Applications")
+                    .arg(&q1);
+                run_mdfind(cmd, Duration::from_secs(3), &cancel_a).await
             }));
 
             // 任务 B: 外接盘
             let q_vol = final_query.clone();
+            let cancel_b = cancel.clone();
             tasks.push(tokio::spawn(async move {
-                let output = tokio::time::timeout(
-                    std::time::Duration::from_secs(4),
-                    AsyncCommand::new("mdfind")
-                        .arg("-onlyin").arg("/Volumes")
-                        .arg(&q_vol)
-                        .output()
-                ).await;
-                match output {
-                    Ok(Ok(o)) => String::from_utf8_lossy(&o.stdout).to_string(),
-                    _ => String::new(),
-                }
+                let mut cmd = AsyncCommand::new("mdfind");
+                cmd.arg("-onlyin").arg("/Volumes")
+                    .arg(&q_vol);
+                run_mdfind(cmd, Duration::from_secs(4), &cancel_b).await
             }));
 
             // 2. 并行执行所有任务
@@ -463,7 +836,7 @@ async fn search_files_internal(
                     if path.is_empty() || path.contains("/Contents/MacOS/") || path.contains("/Library/") { continue; }
                     
                     let name = path.split('/').next_back().unwrap_or(&path).to_string();
-                    results.push(SearchResult { path, name, score: 0 });
+                    results.push(SearchResult { path, name, score: 0, highlight: None, explain: None });
                 }
             }
             results
@@ -471,236 +844,178 @@ async fn search_files_internal(
     };
 
     let memory_handle = {
-        let keyword_lc = keyword_lc.clone();
         let filter_type = filter_type.clone();
         let index_files = state.index.files.clone();
+        let index_generation = state.index.generation.clone();
         let strategy = SearchStrategy::from_type(&filter_type);
-        let mapping = state.mapping.lock().unwrap().clone();
+        let query = query.clone();
+        let sink = sink.clone();
+        let cancel = cancel.clone();
+        // 同一窗口上一次查询的候选集，可用于递进式输入的缩小搜索
+        let session = session.map(|(window, seq)| (state.sessions.clone(), window, seq));
+        // 流式模式下内存任务需要自行打分
+        let history = if sink.is_some() { state.click_history.lock().unwrap().clone() } else { ClickHistory::default() };
+        let profile = state.ranking_profile(&filter_type);
         
-        tokio::spawn(async move {
-            let mut results = Vec::new();
-            let mut fallback_results = Vec::new();
+        // 扫描本身在多个线程上并行进行，放到阻塞线程池避免占用异步运行时
+        tokio::task::spawn_blocking(move || {
             let start = std::time::Instant::now();
-            let mapped_keyword = mapping.get(&keyword_lc).cloned();
             
             let volumes_exist: std::collections::HashSet<String> = if let Ok(entries) = std::fs::read_dir("/Volumes") {
-                entries.flatten().map(|e| e.path().to_string_lossy().to_string()).collect()
+                entries.flatten().map(|e| e.file_name().to_string_lossy().to_string()).collect()
             } else {
                 std::collections::HashSet::new()
             };
 
-            let words: Vec<&str> = keyword_lc.split_whitespace().collect();
-            let guard = index_files.lock().unwrap();
-            
-            for path in guard.iter() {
+            let guard = index_files.read().unwrap();
+            let generation = index_generation.load(Ordering::Relaxed);
+
+            // 新查询是上一次的细化 (词在末尾加长、类型更窄) 时只需复查上次的候选；新增 AND 词仍全量扫描
+            let narrowed = session.as_ref().and_then(|(sessions, window, _)| sessions.cached_scan(window, &query, &filter_type, generation));
+            if let Some(prev) = narrowed.as_ref() {
+                println!("递进搜索: 复用上次的 {} 个候选", prev.candidates.len());
+            }
+
+            let accept = |path: &str, path_lc: &str| {
                 // 1. 类型预过滤 (使用 Strategy 解耦)
                 if filter_type != "all" {
                     if filter_type == "folder" {
                         // 改进文件夹判断逻辑：不包含点，或者是以 .app 结尾的目录（在 macOS 中 app 也是文件夹）
                         let is_likely_dir = !path.contains('.') || path.ends_with(".app");
-                        if !is_likely_dir { continue; }
-                    } else if !strategy.matches_extension(path) {
-                        continue;
+                        if !is_likely_dir { return false; }
+                    } else if !strategy.matches_extension(path_lc) {
+                        return false;
                     }
                 }
 
                 // 2. 快速排除离线外接盘
-                if path.starts_with("/Volumes/") {
-                    let parts: Vec<&str> = path.split('/').collect();
-                    if parts.len() >= 3 {
-                        let vol_path = format!("/Volumes/{}", parts[2]);
-                        if !volumes_exist.contains(&vol_path) { continue; }
+                if let Some(rest) = path.strip_prefix("/Volumes/") {
+                    if let Some((volume, _)) = rest.split_once('/') {
+                        if !volumes_exist.contains(volume) { return false; }
                     }
                 }
+                true
+            };
+            let to_result = |i: usize| SearchResult { path: guard.path(i).to_string(), name: guard.name(i).to_string(), score: 0, highlight: None, explain: None };
 
-                let name = path.split('/').next_back().unwrap_or(path).to_string();
-                let name_lc = name.to_lowercase();
-                let path_lc = path.to_lowercase();
-                
-                // 3. 多词匹配逻辑 (仿 Everything：多词 AND 匹配)
-                let mut matched_count = 0;
-                for word in &words {
-                    if name_lc.contains(word) || path_lc.contains(word) {
-                        matched_count += 1;
-                    }
+            let push_batch = |batch: &[usize]| {
+                if sink.is_some() {
+                    let mut pending: Vec<SearchResult> = batch.iter().map(|&i| to_result(i)).collect();
+                    flush_batch(sink.as_ref(), &mut pending, &query, &filter_type, &history, &profile);
                 }
+            };
 
-                // 4. 别名与缩写补充逻辑
-                if matched_count < words.len() {
-                    // 别名映射
-                    if let Some(en_name) = mapped_keyword.as_ref() {
-                        if name_lc.contains(en_name) {
-                            matched_count = words.len();
-                        }
-                    }
-                    // 自动缩写 (如 dpp -> Digital Photo Professional)
-                    if matched_count < words.len() && keyword_lc.len() >= 2 {
-                        let initials: String = name_lc
-                            .split(|c: char| !c.is_alphanumeric())
-                            .filter(|s| !s.is_empty())
-                            .map(|s| s.chars().next().unwrap_or(' '))
-                            .collect();
-                        if initials.contains(&keyword_lc) {
-                            matched_count = words.len();
-                        }
+            // 3. 大索引先用三元组索引圈定严格命中的候选；结果够一页时不再全量扫描，
+            //    此时跳过缩写、部分匹配与模糊兜底，结果标记为不完整，候选集也不缓存给递进搜索
+            let mut complete = true;
+            let shortlisted = match narrowed {
+                Some(_) => None,
+                None => guard.shortlist(&query).and_then(|list| {
+                    let hits = guard.scan(&query, Some(&list), &cancel, accept, |_: &[usize]| {});
+                    println!("三元组索引圈定 {} 个候选, 严格命中 {} 条", list.len(), hits.full.len());
+                    (hits.full.len() >= DEFAULT_PAGE_SIZE).then_some(hits)
+                }),
+            };
+            // 4. 多词 AND 匹配 + 别名/缩写 + 模糊兜底，严格命中的结果在扫描过程中分批推送
+            let hits = match shortlisted {
+                Some(hits) => {
+                    complete = false;
+                    for batch in hits.full.chunks(STREAM_BATCH_SIZE) {
+                        push_batch(batch);
                     }
+                    hits
                 }
-                
-                if matched_count == words.len() {
-                    results.push(SearchResult { path: path.clone(), name, score: 0 });
-                } else if matched_count > 0 && words.len() > 1 {
-                    // 记录部分匹配的结果，作为 fallback
-                    fallback_results.push(SearchResult { path: path.clone(), name, score: 0 });
-                }
+                None => guard.scan(&query, narrowed.as_ref().map(|p| p.candidates.as_slice()), &cancel, accept, push_batch),
+            };
 
-                if results.len() > 1000 { break; }
-            }
+            let mut results: Vec<SearchResult> = hits.full.iter().map(|&i| to_result(i)).collect();
 
             // 如果严格匹配结果太少，合并部分匹配的结果
+            let mut extra = Vec::new();
             if results.len() < 20 {
-                results.extend(fallback_results.into_iter().take(50));
+                extra.extend(hits.partial.iter().take(50).map(|&i| to_result(i)));
             }
 
-            println!("内存索引搜索耗时: {:?}", start.elapsed());
-            results
+            // 模糊结果排在精确结果之后，由统一排序决定先后
+            extra.extend(hits.fuzzy.iter().take(200).map(|&i| to_result(i)));
+
+            if sink.is_some() {
+                let mut pending = extra.clone();
+                flush_batch(sink.as_ref(), &mut pending, &query, &filter_type, &history, &profile);
+            }
+            results.extend(extra);
+
+            // 记录本次候选集，供同一会话的下一次细化查询使用
+            if let Some((sessions, window, seq)) = session {
+                if complete && !hits.candidates_truncated && !cancel.is_cancelled() {
+                    sessions.store_scan(&window, seq, ScanCache {
+                        query: query.clone(),
+                        filter_type: filter_type.clone(),
+                        generation,
+                        candidates: Arc::new(hits.candidates),
+                    });
+                }
+            }
+
+            println!("内存索引搜索耗时: {:?} (共 {} 条索引)", start.elapsed(), guard.count());
+            (results, complete)
         })
     };
 
     // 等待所有并行任务完成
     let (spotlight_res, memory_res) = tokio::join!(spotlight_handle, memory_handle);
-    let spotlight_results = spotlight_res.unwrap_or_default();
-    let memory_results = memory_res.unwrap_or_default();
+    if cancel.is_cancelled() {
+        println!("搜索已被新的查询取代: keyword='{}'", keyword);
+        return Ok(SearchOutput { results: Vec::new(), complete: false });
+    }
+    let mut spotlight_results = spotlight_res.unwrap_or_default();
+    let (memory_results, complete) = memory_res.unwrap_or_default();
+
+    // Spotlight 只支持不区分大小写的子串匹配，严格选项下按同一规则复核
+    if query.options.case_sensitive || query.options.whole_word {
+        spotlight_results.retain(|r| query.evaluate(&r.name, &r.path).is_full());
+    }
     
     println!("Spotlight 返回: {} 条, 内存索引返回: {} 条", spotlight_results.len(), memory_results.len());
     
-    let mut all_results = [spotlight_results, memory_results].concat();
-
     // 2. 移除重复项并预计算权重
-    let mut seen = std::collections::HashSet::new();
+    let (mut all_results, mut spotlight_only) = merge_results(memory_results, spotlight_results);
     let history = state.click_history.lock().unwrap().clone();
-    let mapping = state.mapping.lock().unwrap().clone();
-    let mapped_keyword = mapping.get(&keyword_lc).cloned();
-    
-    all_results.retain(|r| seen.insert(r.path.clone()));
+    let profile = state.ranking_profile(&filter_type);
 
     for res in all_results.iter_mut() {
-        let name_lc = res.name.to_lowercase();
-        let path_lc = res.path.to_lowercase();
-        
-        // A. 基础匹配权重 (智能多词加权)
-        let mut base_score = 0;
-
-        let words: Vec<&str> = keyword_lc.split_whitespace().collect();
-        let mut all_in_name = words.iter().all(|w| name_lc.contains(w));
-        let all_in_path = words.iter().all(|w| path_lc.contains(w));
-
-        // 别名与缩写支持 (Acronym)
-        let mut is_alias_match = false;
-        let mut is_acronym_match = false;
-
-        // 1. 静态别名映射 (如 ps -> photoshop)
-        if let Some(en_name) = mapped_keyword.as_ref() {
-            if name_lc.contains(en_name) {
-                all_in_name = true;
-                is_alias_match = true;
-            }
-        }
-
-        // 2. 自动缩写匹配 (如 dpp -> Digital Photo Professional)
-        if !all_in_name && keyword_lc.len() >= 2 {
-            let initials: String = name_lc
-                .split(|c: char| !c.is_alphanumeric())
-                .filter(|s| !s.is_empty())
-                .map(|s| s.chars().next().unwrap_or(' '))
-                .collect();
-            if initials.contains(&keyword_lc) {
-                all_in_name = true;
-                is_acronym_match = true;
-            }
-        }
-
-        // 权重分配逻辑优化
-        if all_in_name {
-            if is_alias_match || is_acronym_match || name_lc == keyword_lc {
-                base_score += 20000; // 进一步提高权重，确保绝对置顶
-            } else {
-                // 检查连续性
-                let mut is_continuous = true;
-                let mut last_pos = 0;
-                for word in words.iter() {
-                    if let Some(pos) = name_lc[last_pos..].find(word) {
-                        last_pos += pos + word.len();
-                    } else {
-                        is_continuous = false;
-                        break;
-                    }
-                }
-                
-                if is_continuous {
-                    base_score += 10000;
-                    if name_lc.starts_with(words[0]) {
-                        base_score += 5000; // 增加开头匹配加成
-                    }
-                } else {
-                    base_score += 5000;
-                }
-            }
-        } else if all_in_path {
-            base_score += 2000;
-        }
-
-        // 针对程序类的特殊加成
-        if filter_type == "app"
-            && (res.path.ends_with(".app") || res.path.ends_with(".app/")) {
-            base_score += 10000; // 确保 .app 目录排在其他文件前面
-        }
-
-        // B. 点击历史加成 (权重最高，体现自学习)
-        if let Some(&clicks) = history.get(&res.path) {
-            base_score += (clicks as i32) * 5000; // 显著提高点击权重
-        }
-
-        // C. 路径深度与嵌套惩罚
-        let depth = res.path.split('/').count() as i32;
-        
-        // 惩罚嵌套在 .app 包内部的子程序 (如 Digital Photo Professional 4.app/Contents/Resources/...)
-        if res.path.contains(".app/Contents/") {
-            base_score -= 10000; 
-        }
-
-        if !res.path.starts_with("/Applications") {
-            base_score -= depth * 50;
-        }
-
-        // D. 位置权重
-        if res.path.starts_with("/Applications") {
-            base_score += 5000; // 提高应用目录基础分
-        } else if res.path.contains("/Desktop") {
-            base_score += 1000;
-        }
-
-        res.score = base_score;
+        ranking::rank_result(res, &query, &filter_type, &history, &profile);
     }
 
+    // 流式模式：内存结果已在扫描过程中推送，这里补发 Spotlight 独有的结果
+    flush_batch(sink.as_ref(), &mut spotlight_only, &query, &filter_type, &history, &profile);
+
     // 3. 最终排序 (仅根据预计算的 score)
     all_results.sort_by(|a, b| b.score.cmp(&a.score));
 
-    let final_results: Vec<SearchResult> = all_results.into_iter().take(100).collect();
-    println!("搜索极速完成: 耗时: {:?}", start_time.elapsed());
+    println!("搜索极速完成: 共 {} 条, 耗时: {:?}", all_results.len(), start_time.elapsed());
     
-    Ok(final_results)
+    Ok(SearchOutput { results: all_results, complete })
+}
+
+/// 合并内存索引与 Spotlight 的结果并去重，返回合并结果及其中 Spotlight 独有的部分。
+/// 两边可能分别返回 NFD/NFC 形式的同一路径，按归一化后的路径去重，重复时保留内存索引的
+fn merge_results(mut memory: Vec<SearchResult>, mut spotlight: Vec<SearchResult>) -> (Vec<SearchResult>, Vec<SearchResult>) {
+    let mut seen = std::collections::HashSet::new();
+    memory.retain(|r| seen.insert(matcher::normalize(&r.path)));
+    spotlight.retain(|r| seen.insert(matcher::normalize(&r.path)));
+    let mut all = memory;
+    all.extend(spotlight.iter().cloned());
+    (all, spotlight)
 }
 
 #[tauri::command]
-fn open_file(path: String, state: State<'_, AppCache>) -> Result<(), String> {
+fn open_file(path: String, query: Option<String>, state: State<'_, AppCache>) -> Result<(), String> {
     // 记录点击，实现自我学习
-    {
-        let mut history = state.click_history.lock().unwrap();
-        let count = history.entry(path.clone()).or_insert(0);
-        *count += 1;
-        println!("自我学习: 用户点击了 {}, 当前点击次数: {}", path, count);
+    match state.record_open(&path, query.as_deref()) {
+        Some(count) => println!("自我学习: 用户点击了 {}, 当前点击次数: {}", path, count),
+        None => println!("无痕或已排除的路径，不记录: {}", path),
     }
-    state.save_click_history();
 
     Command::new("open")
         .arg(&path)
@@ -710,15 +1025,12 @@ fn open_file(path: String, state: State<'_, AppCache>) -> Result<(), String> {
 }
 
 #[tauri::command]
-fn open_folder(path: String, state: State<'_, AppCache>) -> Result<(), String> {
+fn open_folder(path: String, query: Option<String>, state: State<'_, AppCache>) -> Result<(), String> {
     // 记录点击
-    {
-        let mut history = state.click_history.lock().unwrap();
-        let count = history.entry(path.clone()).or_insert(0);
-        *count += 1;
-        println!("自我学习: 用户打开了 {} 的位置, 当前点击次数: {}", path, count);
+    match state.record_open(&path, query.as_deref()) {
+        Some(count) => println!("自我学习: 用户打开了 {} 的位置, 当前点击次数: {}", path, count),
+        None => println!("无痕或已排除的路径，不记录: {}", path),
     }
-    state.save_click_history();
 
     let folder_path = if std::path::Path::new(&path).is_dir() {
         path
@@ -770,11 +1082,8 @@ async fn copy_to_clipboard(path: String) -> Result<(), String> {
 }
 
 #[tauri::command]
-fn record_click(path: String, state: State<'_, AppCache>) -> Result<(), String> {
-    let mut history = state.click_history.lock().unwrap();
-    let count = history.entry(path).or_insert(0);
-    *count += 1;
-    state.save_click_history();
+fn record_click(path: String, query: Option<String>, state: State<'_, AppCache>) -> Result<(), String> {
+    state.record_open(&path, query.as_deref());
     Ok(())
 }
 
@@ -784,6 +1093,171 @@ fn trigger_index_update(state: State<'_, AppCache>) -> Result<(), String> {
     Ok(())
 }
 
+/// 清空全部点击历史 (含按查询学习的记录)
+#[tauri::command]
+fn clear_click_history(state: State<'_, AppCache>) -> Result<(), String> {
+    state.change_history(HistoryOp::Clear);
+    Ok(())
+}
+
+/// 删除某个路径的点击历史，返回是否有记录
+#[tauri::command]
+fn forget_click_history(path: String, state: State<'_, AppCache>) -> Result<bool, String> {
+    let found = state.click_history.lock().unwrap().paths().binary_search(&path).is_ok();
+    if found {
+        state.change_history(HistoryOp::Forget { path });
+    }
+    Ok(found)
+}
+
+/// 重新读取排序配置 (ranking.json)，格式错误时返回原因并保留当前方案
+#[tauri::command]
+fn reload_ranking_config(state: State<'_, AppCache>) -> Result<(), String> {
+    state.load_ranking_config()
+}
+
+/// 读取全部设置 (键 -> JSON 值)
+#[tauri::command]
+fn get_settings(state: State<'_, AppCache>) -> Result<HashMap<String, serde_json::Value>, String> {
+    state.store.settings()
+}
+
+/// 保存一项设置，值为 null 时删除
+#[tauri::command]
+fn set_setting(key: String, value: serde_json::Value, state: State<'_, AppCache>) -> Result<(), String> {
+    state.store.set_setting(&key, &value)
+}
+
+#[tauri::command]
+fn list_saved_searches(state: State<'_, AppCache>) -> Result<Vec<SavedSearch>, String> {
+    state.store.saved_searches()
+}
+
+/// 保存当前搜索，同名时覆盖
+#[tauri::command]
+fn save_search(mut search: SavedSearch, state: State<'_, AppCache>) -> Result<(), String> {
+    if search.name.trim().is_empty() {
+        return Err("搜索名称不能为空".to_string());
+    }
+    search.created_at = history::unix_now();
+    state.store.save_search(&search)
+}
+
+/// 删除保存的搜索，返回是否存在
+#[tauri::command]
+fn delete_saved_search(name: String, state: State<'_, AppCache>) -> Result<bool, String> {
+    state.store.delete_search(&name)
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AliasReport {
+    /// 生效的别名及其来源 (内置、应用名、用户)
+    aliases: Vec<AliasEntry>,
+    /// 同一别名有多个目标时被覆盖的定义
+    conflicts: Vec<AliasConflict>,
+}
+
+/// 当前生效的全部别名与冲突报告
+#[tauri::command]
+fn list_aliases(state: State<'_, AppCache>) -> AliasReport {
+    let table = state.aliases.read().unwrap();
+    AliasReport { aliases: table.entries.clone(), conflicts: table.conflicts.clone() }
+}
+
+/// 添加或修改别名 (写入 aliases.json)，`targets` 按优先顺序排列，立即生效
+#[tauri::command]
+fn set_alias(alias: String, targets: Vec<String>, state: State<'_, AppCache>) -> Result<(), String> {
+    let alias = alias.trim();
+    let targets: Vec<String> = targets.iter().map(|t| t.trim().to_string()).filter(|t| !t.is_empty()).collect();
+    if alias.is_empty() || targets.is_empty() {
+        return Err("别名和目标都不能为空".to_string());
+    }
+    state.edit_user_aliases(|user| user.set(alias, &targets))
+}
+
+/// 移除别名，内置或应用名提供的别名也会被屏蔽；返回移除前是否生效
+#[tauri::command]
+fn remove_alias(alias: String, state: State<'_, AppCache>) -> Result<bool, String> {
+    let alias = alias.trim();
+    let found = state.aliases.read().unwrap().mapping.contains_key(&matcher::fold(alias));
+    state.edit_user_aliases(|user| user.remove(alias))?;
+    Ok(found)
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PrivacySettings {
+    private_mode: bool,
+    exclusions: Vec<String>,
+}
+
+#[tauri::command]
+fn get_privacy_settings(state: State<'_, AppCache>) -> PrivacySettings {
+    PrivacySettings {
+        private_mode: state.private_mode.load(Ordering::Relaxed),
+        exclusions: state.privacy_exclusions.read().unwrap().patterns().to_vec(),
+    }
+}
+
+/// 开关无痕模式：开启后打开文件不记录历史，也不学习查询选择
+#[tauri::command]
+fn set_private_mode(enabled: bool, state: State<'_, AppCache>) -> Result<(), String> {
+    state.store.set_setting(PRIVATE_MODE_SETTING, &serde_json::Value::Bool(enabled))?;
+    state.private_mode.store(enabled, Ordering::Relaxed);
+    Ok(())
+}
+
+/// 设置从不记录的路径规则 (写法见 privacy.rs)，已有记录中匹配的路径随即清除；返回清除的路径数
+#[tauri::command]
+fn set_privacy_exclusions(patterns: Vec<String>, state: State<'_, AppCache>) -> Result<usize, String> {
+    // 先校验规则，无效时不保存
+    PathPatterns::new(&patterns, None)?;
+    state.store.set_setting(PRIVACY_EXCLUSIONS_SETTING, &serde_json::json!(patterns))?;
+    state.set_privacy_exclusions(patterns)
+}
+
+/// 把学习到的数据 (点击历史与按查询学习的选择) 导出为 JSON 文件，`wipe` 为真时导出成功后清空；返回导出的路径数
+#[tauri::command]
+fn export_click_history(path: String, wipe: bool, state: State<'_, AppCache>) -> Result<usize, String> {
+    let (json, count) = {
+        let history = state.click_history.lock().unwrap();
+        (history.to_json(), history.paths().len())
+    };
+    std::fs::write(&path, json).map_err(|e| format!("导出失败: {}", e))?;
+    if wipe {
+        state.change_history(HistoryOp::Clear);
+        // 立即写入数据库，不等后台防抖
+        state.history_writer.flush();
+    }
+    Ok(count)
+}
+
+/// 当前使用的配置、缓存与数据目录
+#[tauri::command]
+fn get_data_dirs() -> paths::AppDirs {
+    paths::app_dirs().clone()
+}
+
+/// 存储密钥的十六进制形式，供用户备份；密钥丢失时凭它恢复数据库
+#[tauri::command]
+fn get_recovery_key(state: State<'_, AppCache>) -> String {
+    state.cipher.key_hex()
+}
+
+/// 恢复备份的存储密钥，重启后生效；之前无法解开而改名保留的数据库改回 store.db 即可读取
+#[tauri::command]
+fn restore_recovery_key(key: String) -> Result<(), String> {
+    let key = crypto::parse_key(&key)?;
+    crypto::store_key(paths::app_dirs(), &key)
+}
+
+/// 各扫描根目录最近一次扫描的条目数与耗时
+#[tauri::command]
+fn get_index_roots(state: State<'_, AppCache>) -> Result<Vec<IndexRoot>, String> {
+    state.store.index_roots()
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     let app_cache = AppCache::new();
@@ -825,6 +1299,38 @@ pub fn run() {
                         .and_then(|t| t.value.as_str())
                         .unwrap_or("all")
                         .to_string();
+                    let flag = |name: &str| matches.args.get(name)
+                        .and_then(|a| a.value.as_bool())
+                        .unwrap_or(false);
+                    let options = SearchOptions {
+                        fuzzy: !flag("exact"),
+                        chinese_variants: !flag("no-variants"),
+                        case_sensitive: flag("case-sensitive"),
+                        whole_word: flag("whole-word"),
+                        match_path: !flag("name-only"),
+                    };
+                    let number = |name: &str| matches.args.get(name)
+                        .and_then(|a| a.value.as_str())
+                        .and_then(|v| v.parse::<usize>().ok());
+                    let offset = number("offset").unwrap_or(0);
+                    let limit = number("limit").unwrap_or(10);
+                    let print_all = flag("all");
+                    let json = flag("json");
+                    let explain = flag("explain");
+                    let text = |name: &str| matches.args.get(name).and_then(|a| a.value.as_str());
+                    let sort_order = (|| -> Result<SortOrder, String> {
+                        Ok(SortOrder {
+                            key: text("sort").map(SortKey::parse).transpose()?.unwrap_or_default(),
+                            direction: text("order").map(SortDirection::parse).transpose()?,
+                        })
+                    })();
+                    let sort_order = match sort_order {
+                        Ok(order) => order,
+                        Err(e) => {
+                            eprintln!("{}", e);
+                            std::process::exit(1);
+                        }
+                    };
                     
                     if !query.is_empty() {
                         is_cli_mode = true;
@@ -834,10 +1340,46 @@ pub fn run() {
                         
                         tauri::async_runtime::spawn(async move {
                             // 执行搜索逻辑 (复用 search_files 的内部逻辑)
-                            match search_files_internal(query, filter_type, state_inner).await {
-                                Ok(results) => {
-                                    for res in results.iter().take(10) {
-                                        println!("{} -> {}", res.name, res.path);
+                            // 按相关度输出时可以边找边打印，其余排序方式须等全部结果到齐
+                            if print_all && !explain && sort_order.key == SortKey::Relevance && sort_order.direction.is_none() {
+                                // 流式输出全部结果，找到一批打印一批
+                                let printed = stream_search(query, filter_type, options, state_inner, 0, CancelToken::new(), None, move |batch| {
+                                    for res in &batch.results {
+                                        print_cli_result(res, json);
+                                    }
+                                    if batch.done && !batch.complete {
+                                        eprintln!("{}", INCOMPLETE_NOTE);
+                                    }
+                                }).await;
+                                match printed {
+                                    Ok(_) => std::process::exit(0),
+                                    Err(e) => {
+                                        eprintln!("搜索出错: {}", e);
+                                        std::process::exit(1);
+                                    }
+                                }
+                            }
+                            let explain_query = {
+                                let aliases = state_inner.aliases.read().unwrap();
+                                Query::new(&query, options.clone(), &aliases.mapping)
+                            };
+                            match search_files_internal(query, filter_type.clone(), options, state_inner.clone(), None, CancelToken::new(), None).await {
+                                Ok(output) => {
+                                    let history = state_inner.click_history.lock().unwrap().clone();
+                                    let results = sort::sort_results(output.results, sort_order, &history);
+                                    let limit = if print_all { usize::MAX } else { limit };
+                                    for res in results.iter().skip(offset).take(limit) {
+                                        if explain {
+                                            let mut res = res.clone();
+                                            res.explain = Some(ranking::explain(&res, &explain_query, &filter_type, &history, &state_inner.ranking_profile(&filter_type)));
+                                            print_cli_result(&res, json);
+                                        } else {
+                                            print_cli_result(res, json);
+                                        }
+                                    }
+                                    eprintln!("共 {} 条匹配", results.len());
+                                    if !output.complete {
+                                        eprintln!("{}", INCOMPLETE_NOTE);
                                     }
                                     std::process::exit(0);
                                 }
@@ -880,35 +1422,151 @@ pub fn run() {
             });
 
             // 启动后台索引任务
-            cache_clone.index.start_indexing();
+            let cache_for_scan = cache_clone.clone();
+            cache_clone.index.start_indexing(move |files, roots| cache_for_scan.after_scan(files, roots));
             
-            // 后台映射更新任务 (每小时更新一次别名表)
+            // 后台映射更新任务：每小时重建一次别名表，aliases.json 被修改后立即重新加载
             let cache_for_update = cache_clone.clone();
             tauri::async_runtime::spawn(async move {
+                let alias_path = paths::app_dirs().alias_config_path();
+                let modified = || std::fs::metadata(&alias_path).and_then(|m| m.modified()).ok();
+                let mut last_modified = modified();
+                let mut last_update = std::time::Instant::now();
                 loop {
-                    cache_for_update.update();
-                    sleep(Duration::from_secs(3600)).await;
+                    sleep(Duration::from_secs(2)).await;
+                    let current = modified();
+                    if current != last_modified || last_update.elapsed() >= Duration::from_secs(3600) {
+                        if current != last_modified {
+                            println!("别名配置已修改，重新加载");
+                        }
+                        last_modified = current;
+                        last_update = std::time::Instant::now();
+                        cache_for_update.update();
+                    }
                 }
             });
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             search_files, 
+            search_files_stream,
             open_file, 
             open_folder, 
             record_click,
             get_indexing_status,
             trigger_index_update,
+            reload_ranking_config,
+            clear_click_history,
+            forget_click_history,
+            get_settings,
+            set_setting,
+            list_saved_searches,
+            save_search,
+            delete_saved_search,
+            list_aliases,
+            set_alias,
+            remove_alias,
+            get_index_roots,
+            get_data_dirs,
+            get_recovery_key,
+            get_privacy_settings,
+            set_private_mode,
+            set_privacy_exclusions,
+            export_click_history,
+            restore_recovery_key,
             copy_to_clipboard
         ])
         .build(tauri::generate_context!())
         .expect("error while building tauri application")
-        .run(|app_handle, event| {
-            if let tauri::RunEvent::Reopen { .. } = event {
+        .run(|app_handle, event| match event {
+            tauri::RunEvent::Reopen { .. } => {
                 if let Some(window) = app_handle.get_webview_window("main") {
                     let _ = window.show();
                     let _ = window.set_focus();
                 }
             }
+            // 退出前写入尚未保存的点击历史
+            tauri::RunEvent::Exit => app_handle.state::<AppCache>().history_writer.flush(),
+            _ => {}
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(path: &str) -> SearchResult {
+        let name = path.rsplit('/').next().unwrap_or(path).to_string();
+        SearchResult { path: path.to_string(), name, score: 0, highlight: None, explain: None }
+    }
+
+    fn paths(results: &[SearchResult]) -> Vec<&str> {
+        results.iter().map(|r| r.path.as_str()).collect()
+    }
+
+    #[test]
+    fn pages_report_total_independent_of_offset_and_limit() {
+        let results: Vec<SearchResult> = (0..250).map(|i| result(&format!("/r/{}.txt", i))).collect();
+        let (page, total) = page_of(results.clone(), 0, None);
+        assert_eq!((page.len(), total), (DEFAULT_PAGE_SIZE, 250));
+        assert_eq!(page[0].path, "/r/0.txt");
+        let (page, total) = page_of(results.clone(), 240, Some(20));
+        assert_eq!((paths(&page).first().copied(), page.len(), total), (Some("/r/240.txt"), 10, 250));
+        let (page, total) = page_of(results, 300, Some(20));
+        assert!(page.is_empty() && total == 250);
+    }
+
+    #[test]
+    fn merge_dedups_within_and_across_sources() {
+        // 内存结果自身含重复 (NFD/NFC 两种形式) 时，Spotlight 独有部分仍按来源区分
+        let memory = vec![result("/a/Re\u{301}sume\u{301}.pdf"), result("/a/Résumé.pdf"), result("/a/b.txt")];
+        let spotlight = vec![result("/a/b.txt"), result("/a/c.txt"), result("/a/Résumé.pdf")];
+        let (all, spotlight_only) = merge_results(memory, spotlight);
+        assert_eq!(paths(&all), ["/a/Re\u{301}sume\u{301}.pdf", "/a/b.txt", "/a/c.txt"]);
+        assert_eq!(paths(&spotlight_only), ["/a/c.txt"]);
+
+        let (all, spotlight_only) = merge_results(vec![result("/x"), result("/x")], Vec::new());
+        assert_eq!((all.len(), spotlight_only.len()), (1, 0));
+    }
+
+    #[tokio::test]
+    async fn relayed_batches_carry_running_total_and_finish_with_done() {
+        // 与真实搜索一样在另一个任务中产出结果
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        let search = tokio::spawn(async move {
+            tx.send(vec![result("/a"), result("/b")]).unwrap();
+            tx.send(vec![result("/c")]).unwrap();
         });
+        let search = async { search.await.map_err(|e| e.to_string()).map(|_| true) };
+        let batches = Mutex::new(Vec::new());
+        let total = relay_batches(rx, search, 7, &CancelToken::new(), |b| batches.lock().unwrap().push(b)).await.unwrap();
+        let batches = batches.into_inner().unwrap();
+        assert_eq!(total, 3);
+        let summary: Vec<(u64, usize, usize, bool, bool)> = batches.iter().map(|b| (b.seq, b.results.len(), b.total, b.done, b.complete)).collect();
+        assert_eq!(summary, [(7, 2, 2, false, false), (7, 1, 3, false, false), (7, 0, 3, true, true)]);
+
+        // 只查找了严格命中的搜索在 done 批次上标记为不完整
+        let (_tx, rx) = tokio::sync::mpsc::unbounded_channel::<Vec<SearchResult>>();
+        drop(_tx);
+        let done = Mutex::new(None);
+        relay_batches(rx, async { Ok(false) }, 8, &CancelToken::new(), |b| *done.lock().unwrap() = Some((b.done, b.complete))).await.unwrap();
+        assert_eq!(done.into_inner().unwrap(), Some((true, false)));
+    }
+
+    #[tokio::test]
+    async fn cancelled_relay_emits_nothing_and_errors_propagate() {
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        tx.send(vec![result("/a")]).unwrap();
+        drop(tx);
+        let cancel = CancelToken::new();
+        cancel.cancel();
+        let emitted = Mutex::new(0);
+        relay_batches(rx, async { Ok(true) }, 1, &cancel, |_| *emitted.lock().unwrap() += 1).await.unwrap();
+        assert_eq!(*emitted.lock().unwrap(), 0);
+
+        let (_tx, rx) = tokio::sync::mpsc::unbounded_channel::<Vec<SearchResult>>();
+        drop(_tx);
+        let failed = relay_batches(rx, async { Err("索引未就绪".to_string()) }, 1, &CancelToken::new(), |_| {}).await;
+        assert_eq!(failed, Err("索引未就绪".to_string()));
+    }
 }
@@ -0,0 +1,555 @@
+// 匹配引擎：文本归一化 + 仿 fzf 的子序列打分 + 小编辑距离容错
+//
+// 归一化统一 NFC/NFD (macOS 文件名为 NFD)、全角/半角与大小写，索引与查询两侧必须走同一套规则。
+// 子序列匹配允许跳字 (如 phtshp -> photoshop)，命中单词边界、驼峰、连续字符时加分，
+// 跳过的字符按间隔扣分；容错匹配允许 1~2 个字符的增删改或相邻互换 (如 photoshpo)。
+
+use std::ops::Range;
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+const SCORE_MATCH: i32 = 16;
+const SCORE_GAP_START: i32 = -3;
+const SCORE_GAP_EXTENSION: i32 = -1;
+const BONUS_BOUNDARY: i32 = 8;
+const BONUS_CAMEL: i32 = 7;
+const BONUS_CONSECUTIVE: i32 = 4;
+const BONUS_FIRST_CHAR_MULTIPLIER: i32 = 2;
+
+/// 参与子序列匹配的最短查询长度，过短的词几乎能匹配任何文件名
+pub const MIN_FUZZY_LEN: usize = 3;
+/// 参与容错匹配的最短查询长度
+pub const MIN_TYPO_LEN: usize = 4;
+// 编辑距离 DP 在栈上能容纳的最大行宽
+const STACK_WIDTH: usize = 32;
+
+// 全角 ASCII (！～) 与全角空格转半角，其余字符原样返回
+fn to_half_width(c: char) -> char {
+    match c {
+        '\u{FF01}'..='\u{FF5E}' => char::from_u32(c as u32 - 0xFEE0).unwrap_or(c),
+        '\u{3000}' => ' ',
+        _ => c,
+    }
+}
+
+/// 保留大小写的归一化：NFC 组合 + 全角转半角
+pub fn normalize(s: &str) -> String {
+    if s.is_ascii() {
+        return s.to_string();
+    }
+    s.nfc().map(to_half_width).collect()
+}
+
+/// 匹配用的完整折叠：在 `normalize` 基础上做大小写折叠 (含 ß -> ss、词尾 ς -> σ)
+pub fn fold(s: &str) -> String {
+    if s.is_ascii() {
+        return s.to_ascii_lowercase();
+    }
+    let mut out = String::with_capacity(s.len());
+    for c in s.nfc().map(to_half_width) {
+        match c {
+            'ß' | 'ẞ' => out.push_str("ss"),
+            'ς' => out.push('σ'),
+            'ſ' => out.push('s'),
+            _ => out.extend(c.to_lowercase()),
+        }
+    }
+    out
+}
+
+// ASCII 字母数字在位图中的位置：a-z 占 0~25 位，0-9 占 26~35 位
+fn mask_bit(b: u8) -> Option<u64> {
+    match b.to_ascii_lowercase() {
+        c @ b'a'..=b'z' => Some(1 << (c - b'a')),
+        c @ b'0'..=b'9' => Some(1 << (26 + c - b'0')),
+        _ => None,
+    }
+}
+
+/// 文本中出现过的 ASCII 字母数字位图，用于在逐条匹配前快速排除不可能命中的文件名
+pub fn char_mask(s: &str) -> u64 {
+    s.bytes().filter_map(mask_bit).fold(0, |mask, bit| mask | bit)
+}
+
+/// `word` 中不在位图里的 ASCII 字母数字个数 (按出现次数计)；其余字符无法判断，视为存在
+pub fn missing_chars(word: &str, mask: u64) -> usize {
+    word.bytes().filter_map(mask_bit).filter(|bit| mask & bit == 0).count()
+}
+
+/// 按文件名位图判断 `word` 是否还有可能模糊命中：子序列要求字符全部出现，
+/// 编辑距离为 k 的容错匹配至多缺 k 个字符
+pub fn may_fuzzy_match(word: &str, name_mask: u64) -> bool {
+    let len = word.chars().count();
+    let missing = missing_chars(word, name_mask);
+    (missing == 0 && len >= MIN_FUZZY_LEN) || (typo_budget(len) > 0 && missing <= typo_budget(len))
+}
+
+// 单词字符：字母数字，但中日韩文字之间没有空格分词，一律视为边界
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() && (c as u32) < 0x2E80
+}
+
+/// 全词查找：命中位置前后都必须是单词边界，返回字节偏移
+pub fn find_whole_word(haystack: &str, word: &str) -> Option<usize> {
+    whole_word_matches(haystack, word).next()
+}
+
+/// 所有满足全词条件的命中位置 (字节偏移)
+pub fn whole_word_matches<'a>(haystack: &'a str, word: &'a str) -> impl Iterator<Item = usize> + 'a {
+    haystack.match_indices(word).map(|(i, _)| i).filter(move |&i| {
+        if word.is_empty() {
+            return false;
+        }
+        let before = haystack[..i].chars().next_back();
+        let after = haystack[i + word.len()..].chars().next();
+        let first = word.chars().next();
+        let last = word.chars().next_back();
+        let left_ok = match (before, first) {
+            (Some(b), Some(f)) => !(is_word_char(b) && is_word_char(f)),
+            _ => true,
+        };
+        let right_ok = match (after, last) {
+            (Some(a), Some(l)) => !(is_word_char(a) && is_word_char(l)),
+            _ => true,
+        };
+        left_ok && right_ok
+    })
+}
+
+/// 折叠后的文本，以及每个字节对应的原始字符区间，用于把匹配位置映射回原文 (按 Unicode 字符计)
+pub struct FoldMap {
+    pub text: String,
+    spans: Vec<(usize, usize)>,
+}
+
+impl FoldMap {
+    /// 以「基字符 + 后随组合符」为单位分别折叠，NFC 组合不会跨越这样的单位
+    pub fn new(s: &str, fold: impl Fn(&str) -> String) -> Self {
+        let chars: Vec<char> = s.chars().collect();
+        let mut text = String::with_capacity(s.len());
+        let mut spans = Vec::with_capacity(s.len());
+        let mut start = 0;
+        while start < chars.len() {
+            // 韩文字母的中声、终声 (U+1160..U+11FF) 也会与前面的初声组合
+            let mut end = start + 1;
+            while end < chars.len() && (is_combining_mark(chars[end]) || ('\u{1160}'..='\u{11FF}').contains(&chars[end])) {
+                end += 1;
+            }
+            let segment: String = chars[start..end].iter().collect();
+            let folded = fold(&segment);
+            spans.extend(std::iter::repeat_n((start, end), folded.len()));
+            text.push_str(&folded);
+            start = end;
+        }
+        Self { text, spans }
+    }
+
+    /// 折叠文本中的字节区间对应的原始字符区间 [起, 止)
+    pub fn original(&self, range: Range<usize>) -> [usize; 2] {
+        [self.spans[range.start].0, self.spans[range.end - 1].1]
+    }
+}
+
+fn is_separator(c: char) -> bool {
+    matches!(c, ' ' | '-' | '_' | '.' | '/' | '(' | ')' | '[' | ']' | '&' | '+' | ',')
+}
+
+// 每个位置的边界加分：开头、分隔符之后、驼峰、数字与字母交界
+fn position_bonus(chars: &[char]) -> Vec<i32> {
+    let mut bonus = Vec::with_capacity(chars.len());
+    let mut prev: Option<char> = None;
+    for &c in chars {
+        let b = match prev {
+            None => BONUS_BOUNDARY,
+            Some(p) if is_separator(p) && !is_separator(c) => BONUS_BOUNDARY,
+            Some(p) if p.is_lowercase() && c.is_uppercase() => BONUS_CAMEL,
+            Some(p) if !p.is_numeric() && c.is_numeric() => BONUS_CAMEL,
+            _ => 0,
+        };
+        bonus.push(b);
+        prev = Some(c);
+    }
+    bonus
+}
+
+fn lower_char(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+/// 子序列模糊打分。`query` 须已折叠 (见 `fold`)，`candidate` 为原始文件名 (用于识别驼峰边界)。
+/// 查询的每个字符都按顺序出现在候选中时返回得分，否则返回 None。
+pub fn subsequence_score(query: &str, candidate: &str) -> Option<i32> {
+    let query: Vec<char> = query.chars().filter(|c| !c.is_whitespace()).collect();
+    if query.is_empty() {
+        return None;
+    }
+    // 0. 纯 ASCII 时先在原始字节上做贪心预检，免去不匹配时的分配
+    if candidate.is_ascii() && query.iter().all(char::is_ascii) {
+        let mut rest = query.iter();
+        let mut want = rest.next();
+        for b in candidate.bytes() {
+            if want.is_some_and(|&c| c as u8 == b.to_ascii_lowercase()) {
+                want = rest.next();
+            }
+        }
+        if want.is_some() {
+            return None;
+        }
+    }
+    let original: Vec<char> = normalize(candidate).chars().collect();
+    let text: Vec<char> = original.iter().map(|&c| lower_char(c)).collect();
+
+    // 1. 贪心预检：不是子序列就直接放弃，避免对大量文件名跑 DP
+    let mut qi = 0;
+    for &c in &text {
+        if c == query[qi] {
+            qi += 1;
+            if qi == query.len() {
+                break;
+            }
+        }
+    }
+    if qi < query.len() {
+        return None;
+    }
+
+    // 2. 动态规划求最优对齐 (fzf v2 的简化版)
+    let bonus = position_bonus(&original);
+    let n = text.len();
+    let mut prev_row: Vec<Option<i32>> = vec![None; n];
+
+    for (i, &qc) in query.iter().enumerate() {
+        let mut row: Vec<Option<i32>> = vec![None; n];
+        // 跨越间隔到达当前位置的最优得分 (已计入间隔惩罚)
+        let mut gap_carry: Option<i32> = None;
+
+        for j in 0..n {
+            if text[j] == qc {
+                if i == 0 {
+                    row[j] = Some(SCORE_MATCH + bonus[j] * BONUS_FIRST_CHAR_MULTIPLIER);
+                } else {
+                    // 紧挨上一个命中字符：连续加成；否则按间隔扣分
+                    let diagonal = if j > 0 { prev_row[j - 1] } else { None };
+                    let from_diagonal = diagonal.map(|s| s + SCORE_MATCH + bonus[j].max(BONUS_CONSECUTIVE));
+                    let from_gap = gap_carry.map(|s| s + SCORE_MATCH + bonus[j]);
+                    row[j] = from_diagonal.max(from_gap);
+                }
+            }
+
+            // 更新下一个位置可用的间隔得分
+            if i > 0 && j > 0 {
+                let opened = prev_row[j - 1].map(|s| s + SCORE_GAP_START);
+                let extended = gap_carry.map(|s| s + SCORE_GAP_EXTENSION);
+                gap_carry = opened.max(extended);
+            }
+        }
+
+        prev_row = row;
+    }
+
+    prev_row.into_iter().flatten().max()
+}
+
+/// 子序列命中的各字符位置 (字节偏移)：先正向找到最早的完整匹配，再反向收紧为最短区间 (同 fzf v1)。
+/// `query` 与 `text` 均须已折叠。
+pub fn subsequence_positions(query: &str, text: &str) -> Option<Vec<usize>> {
+    let query: Vec<char> = query.chars().filter(|c| !c.is_whitespace()).collect();
+    if query.is_empty() {
+        return None;
+    }
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    let mut qi = 0;
+    let mut end = None;
+    for (k, &(_, c)) in chars.iter().enumerate() {
+        if c == query[qi] {
+            qi += 1;
+            if qi == query.len() {
+                end = Some(k);
+                break;
+            }
+        }
+    }
+
+    let mut positions = Vec::with_capacity(query.len());
+    for &(offset, c) in chars[..=end?].iter().rev() {
+        if c == query[query.len() - 1 - positions.len()] {
+            positions.push(offset);
+            if positions.len() == query.len() {
+                break;
+            }
+        }
+    }
+    positions.reverse();
+    Some(positions)
+}
+
+/// 受限的 Damerau-Levenshtein (OSA) 距离，超过 `max` 时提前返回 None
+pub fn edit_distance_within<T: PartialEq>(a: &[T], b: &[T], max: usize) -> Option<usize> {
+    if a.len().abs_diff(b.len()) > max {
+        return None;
+    }
+    // 文件名中的单词通常很短，三行 DP 放在栈上，避免逐条分配
+    let width = b.len() + 1;
+    let mut stack = [0usize; 3 * STACK_WIDTH];
+    let mut heap = Vec::new();
+    let rows: &mut [usize] = if width <= STACK_WIDTH {
+        &mut stack[..3 * width]
+    } else {
+        heap.resize(3 * width, 0);
+        &mut heap
+    };
+    let (mut prev2, rest) = rows.split_at_mut(width);
+    let (mut prev, mut cur) = rest.split_at_mut(width);
+    for (j, cell) in prev.iter_mut().enumerate() {
+        *cell = j;
+    }
+
+    for i in 1..=a.len() {
+        cur[0] = i;
+        let mut row_min = cur[0];
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            let mut d = (prev[j] + 1).min(cur[j - 1] + 1).min(prev[j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d = d.min(prev2[j - 2] + 1);
+            }
+            cur[j] = d;
+            row_min = row_min.min(d);
+        }
+        if row_min > max {
+            return None;
+        }
+        std::mem::swap(&mut prev2, &mut prev);
+        std::mem::swap(&mut prev, &mut cur);
+    }
+
+    let d = prev[b.len()];
+    if d <= max { Some(d) } else { None }
+}
+
+/// 根据查询长度决定允许的拼写错误数
+pub fn typo_budget(query_len: usize) -> usize {
+    if query_len >= 8 {
+        2
+    } else if query_len >= MIN_TYPO_LEN {
+        1
+    } else {
+        0
+    }
+}
+
+/// 拼写容错匹配：查询词与文件名中的某个单词 (或其同长度前缀) 的编辑距离在允许范围内。
+/// `query` 与 `name_lc` 均须已折叠，返回实际编辑距离。
+pub fn typo_match(query: &str, name_lc: &str) -> Option<usize> {
+    typo_match_span(query, name_lc).map(|(d, _)| d)
+}
+
+/// 同 `typo_match`，另外返回命中的单词在 `name_lc` 中的字节区间
+pub fn typo_match_span(query: &str, name_lc: &str) -> Option<(usize, Range<usize>)> {
+    let q_len = query.chars().count();
+    let budget = typo_budget(q_len);
+    if budget == 0 {
+        return None;
+    }
+
+    let mut q_chars: Option<Vec<char>> = None;
+    let mut best: Option<(usize, Range<usize>)> = None;
+    for token in name_lc.split(|c: char| !c.is_alphanumeric()).filter(|s| !s.is_empty()) {
+        // 太短的词 (字节数不少于字符数) 整词和前缀都不可能落在容错范围内
+        if token.len() + budget < q_len {
+            continue;
+        }
+        // 与查询相差超过容错数个字符的词同样不可能命中
+        if missing_chars(query, char_mask(token)) > budget {
+            continue;
+        }
+        // 纯 ASCII 时直接按字节比较
+        let d = if query.is_ascii() && token.is_ascii() {
+            token_distance(query.as_bytes(), token.as_bytes(), budget)
+        } else {
+            let q = q_chars.get_or_insert_with(|| query.chars().collect());
+            let t: Vec<char> = token.chars().collect();
+            token_distance(q, &t, budget)
+        };
+        if let Some(d) = d {
+            if best.as_ref().is_none_or(|(b, _)| d < *b) {
+                let start = token.as_ptr() as usize - name_lc.as_ptr() as usize;
+                best = Some((d, start..start + token.len()));
+            }
+        }
+        if best.as_ref().is_some_and(|(d, _)| *d == 0) {
+            break;
+        }
+    }
+    best
+}
+
+// 整词比较，以及与查询等长 (±1) 的前缀比较，支持边输边搜
+fn token_distance<T: PartialEq>(q: &[T], t: &[T], budget: usize) -> Option<usize> {
+    let prefixes = [q.len().saturating_sub(1), q.len(), q.len() + 1]
+        .into_iter()
+        .filter(|&len| len > 0 && len < t.len())
+        .map(|len| &t[..len]);
+    std::iter::once(t).chain(prefixes).filter_map(|c| edit_distance_within(q, c, budget)).min()
+}
+
+/// 单个查询词的模糊匹配结果
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FuzzyKind {
+    /// 子序列命中，附带 fzf 风格得分
+    Subsequence(i32),
+    /// 拼写容错命中，附带编辑距离
+    Typo(usize),
+}
+
+/// 对单个查询词做模糊匹配：先子序列，再拼写容错
+pub fn fuzzy_word(word: &str, name: &str, name_lc: &str) -> Option<FuzzyKind> {
+    let len = word.chars().count();
+    if len >= MIN_FUZZY_LEN {
+        if let Some(score) = subsequence_score(word, name) {
+            return Some(FuzzyKind::Subsequence(score));
+        }
+    }
+    typo_match(word, name_lc).map(FuzzyKind::Typo)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fold_composes_nfd_latin() {
+        // macOS 文件名中的 é 是 e + U+0301
+        let nfd_name = "Re\u{301}sume\u{301}.pdf";
+        assert_eq!(fold(nfd_name), "r\u{e9}sum\u{e9}.pdf");
+        assert!(fold(nfd_name).contains(&fold("résumé")));
+    }
+
+    #[test]
+    fn fold_composes_nfd_hangul() {
+        // 한글 的 NFD 形式为初声/中声/终声字母序列
+        let nfd_name = "\u{1112}\u{1161}\u{11AB}\u{1100}\u{1173}\u{11AF} 문서.txt";
+        assert!(fold(nfd_name).contains(&fold("한글")));
+    }
+
+    #[test]
+    fn fold_composes_nfd_kana() {
+        // 浊音假名 ガ 在 NFD 下是 カ + U+3099
+        let nfd_name = "\u{30AB}\u{3099}\u{30A4}\u{30C9}.pdf";
+        assert!(fold(nfd_name).contains(&fold("ガイド")));
+    }
+
+    #[test]
+    fn fold_full_width_to_half_width() {
+        assert_eq!(fold("ＰＨＯＴＯ　２０２４"), "photo 2024");
+        assert!(fold("报告ＶＩＰ版.docx").contains(&fold("vip")));
+        // 中文字符本身不受影响
+        assert_eq!(fold("简历"), "简历");
+    }
+
+    #[test]
+    fn fold_case() {
+        assert_eq!(fold("Photoshop.APP"), "photoshop.app");
+        assert_eq!(fold("STRASSE"), fold("Straße"));
+        assert_eq!(fold("ΟΔΟΣ"), fold("οδος"));
+        assert_eq!(fold("ÉCOLE"), fold("e\u{301}cole"));
+    }
+
+    #[test]
+    fn whole_word_respects_boundaries() {
+        assert_eq!(find_whole_word("unit test.rs", "test"), Some(5));
+        assert_eq!(find_whole_word("latest.rs", "test"), None);
+        assert_eq!(find_whole_word("testing test", "test"), Some(8));
+        // 中文与英文混排时中文两侧视为边界
+        assert_eq!(find_whole_word("简历test版", "test"), Some(6));
+    }
+
+    #[test]
+    fn normalize_keeps_case() {
+        assert_eq!(normalize("Ｒe\u{301}sume\u{301}"), "R\u{e9}sum\u{e9}");
+    }
+
+    #[test]
+    fn subsequence_scores_boundaries_over_gaps() {
+        assert!(subsequence_score("phtshp", "Adobe Photoshop 2024.app").is_some());
+        assert!(subsequence_score("pshp", "Photoshop.app").is_some());
+        assert!(subsequence_score("shop", "Photoshop.app").is_some());
+        assert!(subsequence_score("phtshpx", "Photoshop.app").is_none());
+        // 命中单词开头与驼峰边界的得分高于散落在单词中间的字符
+        let boundary = subsequence_score("vsc", "Visual Studio Code.app").unwrap();
+        let scattered = subsequence_score("vsc", "Dev Tools Scanner.app").unwrap();
+        assert!(boundary > scattered);
+        let camel = subsequence_score("fc", "FinalCut.app").unwrap();
+        let inner = subsequence_score("fc", "Fabric.app").unwrap();
+        assert!(camel > inner);
+        // 连续命中优于跳字
+        assert!(subsequence_score("phot", "Photoshop.app").unwrap() > subsequence_score("poop", "Photoshop.app").unwrap());
+    }
+
+    #[test]
+    fn edit_distance_counts_transpositions_as_one() {
+        let chars = |s: &str| s.chars().collect::<Vec<_>>();
+        assert_eq!(edit_distance_within(&chars("photoshop"), &chars("photoshop"), 2), Some(0));
+        assert_eq!(edit_distance_within(&chars("photoshpo"), &chars("photoshop"), 2), Some(1));
+        assert_eq!(edit_distance_within(&chars("fotoshop"), &chars("photoshop"), 2), Some(2));
+        assert_eq!(edit_distance_within(&chars("fotoshp"), &chars("photoshop"), 2), None);
+        assert_eq!(edit_distance_within(&chars("ab"), &chars("abcd"), 1), None);
+    }
+
+    #[test]
+    fn typo_budget_grows_with_query_length() {
+        assert_eq!(typo_budget(3), 0);
+        assert_eq!(typo_budget(4), 1);
+        assert_eq!(typo_budget(7), 1);
+        assert_eq!(typo_budget(8), 2);
+        assert_eq!(typo_match("photoshpo", "adobe photoshop.app"), Some(1));
+        assert_eq!(typo_match("potoshopp", "adobe photoshop.app"), Some(2));
+        // 短词不做容错
+        assert_eq!(typo_match("pdg", "report.pdf"), None);
+        assert_eq!(typo_match("reprot", "report.pdf"), Some(1));
+    }
+
+    #[test]
+    fn fuzzy_word_prefers_subsequence_over_typo() {
+        assert!(matches!(fuzzy_word("phtshp", "Photoshop.app", "photoshop.app"), Some(FuzzyKind::Subsequence(_))));
+        assert_eq!(fuzzy_word("photoshpo", "Photoshop.app", "photoshop.app"), Some(FuzzyKind::Typo(1)));
+        assert_eq!(fuzzy_word("zzqx", "Photoshop.app", "photoshop.app"), None);
+    }
+
+    #[test]
+    fn fuzzy_matches_normalized_names() {
+        assert!(subsequence_score(&fold("ｐｈｔｓｈｐ"), "Adobe Photoshop.app").is_some());
+        assert!(subsequence_score(&fold("resume"), "Re\u{301}sume\u{301}.pdf").is_none());
+        assert!(subsequence_score(&fold("résumé"), "Re\u{301}sume\u{301}.pdf").is_some());
+        assert_eq!(typo_match(&fold("ＰＨＯＴＯＳＨＰＯ"), &fold("Adobe Photoshop.app")), Some(1));
+    }
+
+    #[test]
+    fn fold_map_points_back_to_original_chars() {
+        // NFD 的 é 占两个字符，ß 折叠后变成两个字节
+        let map = FoldMap::new("Re\u{301}sume\u{301} Straße.pdf", fold);
+        let pos = map.text.find("résumé").unwrap();
+        assert_eq!(map.original(pos..pos + "résumé".len()), [0, 8]);
+        let pos = map.text.find("strasse").unwrap();
+        assert_eq!(map.original(pos..pos + "strasse".len()), [9, 15]);
+    }
+
+    #[test]
+    fn subsequence_positions_prefer_tight_span() {
+        let text = fold("Adobe Photoshop.app");
+        let positions = subsequence_positions("psp", &text).unwrap();
+        assert_eq!(positions, vec![6, 11, 14]);
+        assert!(subsequence_positions("xyz", &text).is_none());
+    }
+
+    #[test]
+    fn char_mask_prefilter() {
+        let mask = char_mask(&fold("Adobe Photoshop.app"));
+        assert_eq!(missing_chars("phtshp", mask), 0);
+        assert_eq!(missing_chars("photoshpz", mask), 1);
+        assert!(may_fuzzy_match("photoshpz", mask));
+        assert!(!may_fuzzy_match("zzqx", mask));
+        // 非 ASCII 字符不参与判断
+        assert_eq!(missing_chars("简历", mask), 0);
+    }
+}
@@ -0,0 +1,203 @@
+// 数据目录：配置、缓存与数据各放在哪里
+//
+// 默认按平台惯例存放 (均在 com.xtap.search 子目录下)：
+//   macOS  ~/Library/Application Support (配置、数据)、~/Library/Caches (缓存)
+//   Linux  $XDG_CONFIG_HOME、$XDG_DATA_HOME、$XDG_CACHE_HOME (默认 ~/.config、~/.local/share、~/.cache)
+//   Windows %APPDATA% (配置、数据)、%LOCALAPPDATA% (缓存)
+// 可执行文件旁有名为 portable 的文件时进入便携模式，全部数据放在可执行文件旁的 data 目录。
+// 启动时把旧版本写在其它位置的文件迁移过来；目标已存在时不覆盖。
+
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+const APP_ID: &str = "com.xtap.search";
+/// 可执行文件旁存在此文件时启用便携模式
+const PORTABLE_MARKER: &str = "portable";
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AppDirs {
+    /// 用户编辑的配置 (ranking.json、aliases.json)
+    pub config: PathBuf,
+    /// 可随时重新生成的缓存 (文件索引)
+    pub cache: PathBuf,
+    /// 需要保留的数据 (数据库)
+    pub data: PathBuf,
+    pub portable: bool,
+}
+
+/// 一个需要迁移的旧文件
+#[derive(Debug, PartialEq)]
+pub struct Migration {
+    pub from: PathBuf,
+    pub to: PathBuf,
+    /// 复制而不是移动：便携模式从系统目录带走数据时，系统中安装的版本仍可继续使用
+    pub copy: bool,
+}
+
+impl AppDirs {
+    /// 按平台惯例的目录；取不到用户目录时退回临时目录，不会写到根目录
+    pub fn standard() -> Self {
+        let fallback = || {
+            let dir = std::env::temp_dir().join(APP_ID);
+            eprintln!("无法确定用户目录，数据将保存在 {}", dir.display());
+            dir
+        };
+        Self {
+            config: dirs::config_dir().map_or_else(fallback, |d| d.join(APP_ID)),
+            cache: dirs::cache_dir().map_or_else(fallback, |d| d.join(APP_ID)),
+            data: dirs::data_dir().map_or_else(fallback, |d| d.join(APP_ID)),
+            portable: false,
+        }
+    }
+
+    /// 便携模式：全部放在 `root` 下
+    pub fn portable(root: &Path) -> Self {
+        Self { config: root.join("config"), cache: root.join("cache"), data: root.join("data"), portable: true }
+    }
+
+    /// 根据可执行文件旁是否有 portable 文件决定使用哪种布局
+    pub fn resolve() -> Self {
+        let exe_dir = std::env::current_exe().ok().and_then(|exe| exe.parent().map(Path::to_path_buf));
+        match exe_dir {
+            Some(dir) if dir.join(PORTABLE_MARKER).is_file() => Self::portable(&dir.join("data")),
+            _ => Self::standard(),
+        }
+    }
+
+    pub fn index_path(&self) -> PathBuf {
+        self.cache.join("index.cache")
+    }
+
+    pub fn store_path(&self) -> PathBuf {
+        self.data.join("store.db")
+    }
+
+    pub fn ranking_config_path(&self) -> PathBuf {
+        self.config.join("ranking.json")
+    }
+
+    pub fn alias_config_path(&self) -> PathBuf {
+        self.config.join("aliases.json")
+    }
+
+    /// 早期版本的点击历史，迁移到数据目录后由数据库首次创建时导入
+    pub fn legacy_click_history_path(&self) -> PathBuf {
+        self.data.join("click_history.json")
+    }
+
+    /// 旧版本可能写过的文件及其在当前布局中的位置
+    pub fn migrations(&self, home: Option<&Path>, standard: &AppDirs) -> Vec<Migration> {
+        let mut list = Vec::new();
+        let mut add = |from: PathBuf, to: PathBuf, copy: bool| {
+            if from != to {
+                list.push(Migration { from, to, copy });
+            }
+        };
+        // 点击历史原先放在缓存目录
+        add(standard.cache.join("click_history.json"), self.legacy_click_history_path(), false);
+        // 索引原先固定写在 ~/Library/Caches 下，在 Linux 上与缓存目录不一致
+        if let Some(home) = home {
+            add(home.join("Library/Caches").join(APP_ID).join("index.cache"), self.index_path(), false);
+        }
+        if self.portable {
+            add(standard.store_path(), self.store_path(), true);
+            add(standard.ranking_config_path(), self.ranking_config_path(), true);
+            add(standard.alias_config_path(), self.alias_config_path(), true);
+            add(standard.index_path(), self.index_path(), true);
+        }
+        list
+    }
+}
+
+/// 当前使用的数据目录，第一次调用时确定
+pub fn app_dirs() -> &'static AppDirs {
+    static DIRS: OnceLock<AppDirs> = OnceLock::new();
+    DIRS.get_or_init(AppDirs::resolve)
+}
+
+/// 执行迁移，返回迁移成功的文件数；源文件不存在或目标已存在的跳过
+pub fn migrate(migrations: &[Migration]) -> usize {
+    let mut moved = 0;
+    for m in migrations {
+        // 数据库的 WAL 文件与主文件一起迁移，否则最近的写入会丢失
+        let companions = ["", "-wal", "-shm"].map(|suffix| (with_suffix(&m.from, suffix), with_suffix(&m.to, suffix)));
+        if !m.from.is_file() || m.to.exists() {
+            continue;
+        }
+        let result = companions.iter().filter(|(from, _)| from.is_file()).try_for_each(|(from, to)| move_file(from, to, m.copy));
+        match result {
+            Ok(()) => {
+                println!("已迁移 {} -> {}", m.from.display(), m.to.display());
+                moved += 1;
+            }
+            Err(e) => eprintln!("迁移 {} 失败: {}", m.from.display(), e),
+        }
+    }
+    moved
+}
+
+/// 在文件名后追加后缀 (如数据库的 -wal 文件)
+pub fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+fn move_file(from: &Path, to: &Path, copy: bool) -> std::io::Result<()> {
+    if let Some(parent) = to.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    if copy {
+        return std::fs::copy(from, to).map(|_| ());
+    }
+    // 跨文件系统时无法改名，退而复制后删除
+    if std::fs::rename(from, to).is_err() {
+        std::fs::copy(from, to)?;
+        std::fs::remove_file(from)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn migrates_legacy_files_without_overwriting() {
+        let root = std::env::temp_dir().join(format!("xtap-paths-test-{}", std::process::id()));
+        let home = root.join("home");
+        let standard = AppDirs {
+            config: home.join(".config").join(APP_ID),
+            cache: home.join(".cache").join(APP_ID),
+            data: home.join(".local/share").join(APP_ID),
+            portable: false,
+        };
+        let legacy_index = home.join("Library/Caches").join(APP_ID).join("index.cache");
+        std::fs::create_dir_all(legacy_index.parent().unwrap()).unwrap();
+        std::fs::write(&legacy_index, "index").unwrap();
+        std::fs::create_dir_all(&standard.cache).unwrap();
+        std::fs::write(standard.cache.join("click_history.json"), "{}").unwrap();
+
+        let migrations = standard.migrations(Some(&home), &standard);
+        assert_eq!(migrate(&migrations), 2);
+        assert_eq!(std::fs::read_to_string(standard.index_path()).unwrap(), "index");
+        assert!(standard.legacy_click_history_path().is_file());
+        assert!(!legacy_index.exists());
+
+        // 便携模式从系统目录复制，已有的文件不覆盖
+        std::fs::create_dir_all(&standard.data).unwrap();
+        std::fs::write(standard.store_path(), "db").unwrap();
+        std::fs::write(with_suffix(&standard.store_path(), "-wal"), "wal").unwrap();
+        let portable = AppDirs::portable(&root.join("portable"));
+        std::fs::create_dir_all(&portable.cache).unwrap();
+        std::fs::write(portable.index_path(), "mine").unwrap();
+        assert_eq!(migrate(&portable.migrations(Some(&home), &standard)), 1);
+        assert_eq!(std::fs::read_to_string(with_suffix(&portable.store_path(), "-wal")).unwrap(), "wal");
+        assert!(standard.store_path().is_file());
+        assert_eq!(std::fs::read_to_string(portable.index_path()).unwrap(), "mine");
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,91 @@
+// 隐私排除：匹配这些路径的文件打开时不记录，已有的记录也会被清除，因而不参与基于历史的加分
+//
+// 规则写法 (不区分大小写)：
+//   ~/Documents/Personal   该目录及其下的全部内容 (~ 展开为用户目录)
+//   /Volumes/Work/*.key    * 匹配路径中一段内的任意字符，? 匹配一个字符，** 可跨越多段
+//   *.kdbx、Private        不以 / 或 ~ 开头的规则匹配任意位置的同名文件或目录
+
+use regex::Regex;
+
+#[derive(Clone, Debug, Default)]
+pub struct PathPatterns {
+    patterns: Vec<String>,
+    matcher: Option<Regex>,
+}
+
+// 把一条规则翻译为正则；匹配到的目录连同其下内容一起排除
+fn pattern_regex(pattern: &str, home: Option<&str>) -> Option<String> {
+    let expanded = match (pattern.strip_prefix('~'), home) {
+        (Some(rest), Some(home)) => format!("{}{}", home.trim_end_matches('/'), rest),
+        (Some(_), None) => return None,
+        (None, _) => pattern.to_string(),
+    };
+    let trimmed = expanded.trim_end_matches('/');
+    let mut re = String::from(if trimmed.starts_with('/') { "^" } else { "(?:^|/)" });
+    let mut chars = trimmed.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                re.push_str(".*");
+            }
+            '*' => re.push_str("[^/]*"),
+            '?' => re.push_str("[^/]"),
+            _ => re.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+        }
+    }
+    re.push_str("(?:/|$)");
+    Some(re)
+}
+
+impl PathPatterns {
+    /// 编译规则；取不到用户目录时以 ~ 开头的规则不生效
+    pub fn new(patterns: &[String], home: Option<&str>) -> Result<Self, String> {
+        let patterns: Vec<String> = patterns.iter().map(|p| p.trim().to_string()).filter(|p| !p.is_empty()).collect();
+        let parts: Vec<String> = patterns.iter().filter_map(|p| pattern_regex(p, home)).collect();
+        let matcher = if parts.is_empty() {
+            None
+        } else {
+            let joined = format!("(?i)(?:{})", parts.join("|"));
+            Some(Regex::new(&joined).map_err(|e| format!("隐私排除规则无效: {}", e))?)
+        };
+        Ok(Self { patterns, matcher })
+    }
+
+    pub fn patterns(&self) -> &[String] {
+        &self.patterns
+    }
+
+    pub fn matches(&self, path: &str) -> bool {
+        self.matcher.as_ref().is_some_and(|m| m.is_match(path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_directories_globs_and_names() {
+        let patterns: Vec<String> =
+            ["~/Documents/Personal", "/Volumes/Work/*.key", "*.kdbx", "Private/", "/tmp/**/secret?.txt"].map(String::from).to_vec();
+        let rules = PathPatterns::new(&patterns, Some("/Users/me/")).unwrap();
+
+        assert!(rules.matches("/Users/me/Documents/Personal"));
+        assert!(rules.matches("/Users/me/Documents/personal/tax/2024.pdf"));
+        assert!(!rules.matches("/Users/me/Documents/Personal Finance.xlsx"));
+        assert!(rules.matches("/Volumes/Work/plan.key"));
+        assert!(!rules.matches("/Volumes/Work/sub/plan.key"));
+        assert!(rules.matches("/Users/me/vault.KDBX"));
+        assert!(rules.matches("/Users/me/Desktop/Private/photo.jpg"));
+        assert!(!rules.matches("/Users/me/Desktop/NotPrivate/photo.jpg"));
+        assert!(rules.matches("/tmp/a/b/secret1.txt"));
+        assert!(!rules.matches("/tmp/a/b/secret12.txt"));
+
+        // 取不到用户目录时 ~ 规则不生效，其余照常
+        let rules = PathPatterns::new(&patterns, None).unwrap();
+        assert!(!rules.matches("/Users/me/Documents/Personal"));
+        assert!(rules.matches("/Users/me/vault.kdbx"));
+        assert!(!PathPatterns::default().matches("/anything"));
+    }
+}
@@ -0,0 +1,682 @@
+// 查询解析与单条匹配判定
+//
+// 关键词按搜索选项折叠成词项后，对每个文件给出统一的命中结论，内存扫描与排序共用同一套规则，
+// 避免两处各自实现导致「扫到了却排不上」或反之。
+
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use crate::chinese;
+use crate::matcher::{self, FoldMap, FuzzyKind};
+
+/// 单次搜索的可选参数，前端与 CLI 均可按需覆盖
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(default, rename_all = "camelCase")]
+pub struct SearchOptions {
+    /// 模糊匹配 (fzf 风格子序列 + 拼写容错)，默认开启
+    pub fuzzy: bool,
+    /// 简繁体等价匹配 (简历 <-> 簡歷)，默认开启
+    pub chinese_variants: bool,
+    /// 区分大小写，默认关闭
+    pub case_sensitive: bool,
+    /// 全词匹配：词项两侧必须是单词边界，默认关闭
+    pub whole_word: bool,
+    /// 同时匹配完整路径；关闭后只匹配文件名，默认开启
+    pub match_path: bool,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        Self {
+            fuzzy: true,
+            chinese_variants: true,
+            case_sensitive: false,
+            whole_word: false,
+            match_path: true,
+        }
+    }
+}
+
+impl SearchOptions {
+    /// 把文本折叠为比较形式；`fold_case` 为 false 时保留大小写
+    pub fn fold_text(&self, s: &str, fold_case: bool) -> String {
+        let folded = if fold_case { matcher::fold(s) } else { matcher::normalize(s) };
+        if !self.chinese_variants {
+            return folded;
+        }
+        match chinese::to_simplified(&folded) {
+            Cow::Borrowed(_) => folded,
+            Cow::Owned(simplified) => simplified,
+        }
+    }
+
+    /// 按当前选项把文本折叠为比较形式，查询、文件名两侧共用
+    pub fn fold(&self, s: &str) -> String {
+        self.fold_text(s, !self.case_sensitive)
+    }
+
+    /// 区分大小写或全词匹配时用户要的是精确结果，模糊兜底不再生效
+    pub fn fuzzy_enabled(&self) -> bool {
+        self.fuzzy && !self.case_sensitive && !self.whole_word
+    }
+
+    /// 参与模糊打分的文件名 (保留大小写以识别驼峰，简繁模式下转为简体)
+    fn fuzzy_name<'a>(&self, name: &'a str) -> Cow<'a, str> {
+        if self.chinese_variants { chinese::to_simplified(name) } else { Cow::Borrowed(name) }
+    }
+}
+
+/// 单个文件对查询的命中情况
+#[derive(Default)]
+pub struct EntryMatch {
+    /// 在文件名 (或路径) 中直接命中的词项数
+    pub matched_terms: usize,
+    pub all_in_name: bool,
+    pub all_in_path: bool,
+    /// 文件名与整个查询完全相同
+    pub exact_name: bool,
+    /// 所有词项按顺序出现在文件名中
+    pub continuous: bool,
+    /// 文件名以第一个词项开头
+    pub prefix: bool,
+    /// 有词项通过别名映射命中 (如 ps -> photoshop)，值为命中目标的序号 (多个词项经别名命中时为各序号之和)，越小越优先
+    pub alias: Option<usize>,
+    /// 通过单词首字母缩写命中 (如 dpp -> Digital Photo Professional)
+    pub acronym: bool,
+    /// 模糊命中得分，越高越相关
+    pub fuzzy: Option<i32>,
+}
+
+impl EntryMatch {
+    /// 所有词项都以精确、别名或缩写方式命中
+    pub fn is_full(&self) -> bool {
+        self.all_in_name || self.all_in_path
+    }
+}
+
+/// 结果的命中方式，按优先级从高到低
+#[derive(Serialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum MatchKind {
+    /// 文件名与查询完全相同
+    Exact,
+    /// 文件名以第一个词项开头，且所有词项都在文件名中
+    Prefix,
+    /// 所有词项都直接出现在文件名或路径中
+    Contains,
+    Alias,
+    Acronym,
+    Fuzzy,
+    /// 只有部分词项命中 (Spotlight 等来源)
+    Partial,
+}
+
+/// 命中方式与高亮区间。区间为 [起, 止)，按原始文本的 Unicode 字符计 (前端用 Array.from 切分)，
+/// 已排序且互不重叠
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Highlight {
+    pub kind: MatchKind,
+    pub name_ranges: Vec<[usize; 2]>,
+    pub path_ranges: Vec<[usize; 2]>,
+}
+
+// 排序并合并重叠或相邻的区间
+fn merge_ranges(mut ranges: Vec<[usize; 2]>) -> Vec<[usize; 2]> {
+    ranges.sort_unstable();
+    let mut merged: Vec<[usize; 2]> = Vec::with_capacity(ranges.len());
+    for r in ranges {
+        match merged.last_mut() {
+            Some(last) if r[0] <= last[1] => last[1] = last[1].max(r[1]),
+            _ => merged.push(r),
+        }
+    }
+    merged
+}
+
+// 模糊命中的位置：先按子序列逐字标出，不成立时标出拼写最接近的单词 (与 `matcher::fuzzy_word` 顺序一致)
+fn fuzzy_ranges(map: &FoldMap, term: &str) -> Vec<[usize; 2]> {
+    if term.chars().count() >= matcher::MIN_FUZZY_LEN {
+        if let Some(positions) = matcher::subsequence_positions(term, &map.text) {
+            return positions
+                .into_iter()
+                .map(|i| {
+                    let len = map.text[i..].chars().next().map_or(1, char::len_utf8);
+                    map.original(i..i + len)
+                })
+                .collect();
+        }
+    }
+    matcher::typo_match_span(term, &map.text).map(|(_, span)| vec![map.original(span)]).unwrap_or_default()
+}
+
+/// 内存索引中预先折叠好的比较形式 (按默认选项忽略大小写、繁转简)，避免每次查询重新折叠
+pub struct Folded<'a> {
+    pub name: &'a str,
+    pub path: &'a str,
+    /// 文件名与完整路径的字符位图，见 `matcher::char_mask`
+    pub name_mask: u64,
+    pub path_mask: u64,
+}
+
+/// 点击学习使用的查询键：按默认选项折叠并合并空白，同一查询无论搜索选项如何都对应同一个键
+pub fn learning_key(keyword: &str) -> String {
+    SearchOptions::default().fold_text(keyword, true).split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// 按空白切分词项；引号 ("…" 或 “…”) 括起的短语保留内部空白，作为一个词项
+pub fn split_terms(text: &str) -> Vec<String> {
+    let mut terms = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut flush = |current: &mut String| {
+        let term = current.trim();
+        if !term.is_empty() {
+            terms.push(term.to_string());
+        }
+        current.clear();
+    };
+    for c in text.chars() {
+        match c {
+            // 引号本身也是词项边界，未闭合的引号一直括到末尾
+            '"' | '“' | '”' => {
+                flush(&mut current);
+                quoted = !quoted;
+            }
+            c if c.is_whitespace() && !quoted => flush(&mut current),
+            c => current.push(c),
+        }
+    }
+    flush(&mut current);
+    terms
+}
+
+/// 解析后的查询
+#[derive(Clone)]
+pub struct Query {
+    pub options: SearchOptions,
+    /// 见 `learning_key`
+    pub learning_key: String,
+    /// 按选项折叠后的完整查询 (去掉引号，词项以单个空格连接)
+    pub text: String,
+    /// 按选项折叠后的词项，多词为 AND 关系
+    pub terms: Vec<String>,
+    // 忽略大小写的形式，别名、缩写与模糊匹配始终不区分大小写
+    text_ci: String,
+    terms_ci: Vec<String>,
+    // 各词项、整个查询与别名的字符位图，用于按索引位图快速排除
+    term_masks: Vec<u64>,
+    text_mask: u64,
+    alias_masks: Vec<Vec<u64>>,
+    /// 各词项经别名映射的原始目标，按优先顺序，与 `terms` 一一对应。
+    /// 目标是该词项的备选写法，其余词项仍需同时命中 (供 Spotlight 查询与三元组索引)
+    pub term_aliases: Vec<Vec<String>>,
+    term_aliases_ci: Vec<Vec<String>>,
+}
+
+impl Query {
+    pub fn new(keyword: &str, options: SearchOptions, mapping: &HashMap<String, Vec<String>>) -> Self {
+        let terms = split_terms(&options.fold(keyword));
+        let terms_ci: Vec<String> = split_terms(&options.fold_text(keyword, true));
+        let (text, text_ci) = (terms.join(" "), terms_ci.join(" "));
+        let term_masks = terms.iter().map(|t| matcher::char_mask(t)).collect();
+        // 每个词项 (含引号括起的短语) 单独查找别名
+        let term_aliases: Vec<Vec<String>> = terms_ci.iter().map(|t| mapping.get(t).cloned().unwrap_or_default()).collect();
+        let term_aliases_ci: Vec<Vec<String>> =
+            term_aliases.iter().map(|targets| targets.iter().map(|a| options.fold_text(a, true)).collect()).collect();
+        let text_mask = matcher::char_mask(&text_ci);
+        let alias_masks = term_aliases_ci.iter().map(|targets| targets.iter().map(|a| matcher::char_mask(a)).collect()).collect();
+        Self {
+            learning_key: learning_key(keyword),
+            options,
+            text,
+            terms,
+            text_ci,
+            terms_ci,
+            term_masks,
+            text_mask,
+            alias_masks,
+            term_aliases,
+            term_aliases_ci,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    /// 本查询是否是 `previous` 的细化：命中本查询的条目必然也命中过 `previous`
+    /// (每个旧词都包含在某个新词中，每个新词也都包含某个旧词，且别名、选项与拼写容错额度不变；
+    /// 开启模糊匹配时新词只能在旧词后面追加字符)，此时可只在上次的候选集中查找，结果与全量扫描一致。
+    /// 新增的 AND 词不算细化：只命中新词的部分匹配不在旧候选集中。
+    pub fn refines(&self, previous: &Query) -> bool {
+        if self.options != previous.options {
+            return false;
+        }
+        // 经别名扩展的词项在旧查询中必须有同样扩展的同一词项，否则别名目标的命中不在旧候选集中
+        let aliases_kept = self.terms.iter().zip(&self.term_aliases).filter(|(_, targets)| !targets.is_empty()).all(|(term, targets)| {
+            previous.terms.iter().zip(&previous.term_aliases).any(|(old, old_targets)| old == term && old_targets == targets)
+        });
+        if !aliases_kept {
+            return false;
+        }
+        // 全词匹配下 "te" -> "test" 并不单调，无法缩小
+        if self.options.whole_word {
+            return false;
+        }
+        // 过短的旧词没有参与子序列匹配，新词变长后可能出现新的模糊命中
+        if self.options.fuzzy_enabled() && previous.terms_ci.iter().any(|t| t.chars().count() < matcher::MIN_FUZZY_LEN) {
+            return false;
+        }
+        if !previous.terms.iter().all(|old| self.terms.iter().any(|new| new.contains(old.as_str()))) {
+            return false;
+        }
+        // 每个新词都须包含某个旧词：新增的 AND 词会带来只命中它的部分匹配，这些条目不在旧候选集中。
+        // 开启模糊匹配时拼写容错只对追加字符单调 ("abcd" -> "xabcd" 后 xabce 能容错命中，却从未命中 abcd)，
+        // 还须与该旧词的容错额度相同，否则变长的词 (如 3 -> 4、7 -> 8 个字符) 能容忍更多拼写错误
+        let fuzzy = self.options.fuzzy_enabled();
+        let budget = |t: &str| matcher::typo_budget(t.chars().count());
+        self.terms.iter().all(|new| {
+            previous.terms.iter().any(|old| match fuzzy {
+                true => new.starts_with(old.as_str()) && budget(new) == budget(old),
+                false => new.contains(old.as_str()),
+            })
+        })
+    }
+
+    /// 仅凭索引中的字符位图判断条目是否还有可能以任何方式命中 (严格、部分、别名、缩写或模糊)。
+    /// 返回 false 的条目无需读取路径即可跳过，这是大索引扫描的主要加速手段。
+    pub fn may_match(&self, name_mask: u64, path_mask: u64) -> bool {
+        let scope = if self.options.match_path { path_mask } else { name_mask };
+        if self.term_masks.iter().any(|m| m & !scope == 0) {
+            return true;
+        }
+        if self.alias_masks.iter().flatten().any(|m| m & !name_mask == 0) {
+            return true;
+        }
+        if self.text_ci.len() >= 2 && self.text_mask & !name_mask == 0 {
+            return true;
+        }
+        // 没有任何词被直接包含，只剩每个词都模糊命中这一种可能
+        self.options.fuzzy_enabled() && self.terms_ci.iter().all(|t| matcher::may_fuzzy_match(t, name_mask))
+    }
+
+    fn contains_term(&self, haystack: &str, term: &str) -> bool {
+        if self.options.whole_word {
+            matcher::find_whole_word(haystack, term).is_some()
+        } else {
+            haystack.contains(term)
+        }
+    }
+
+    fn find_term(&self, haystack: &str, term: &str) -> Option<usize> {
+        if self.options.whole_word {
+            matcher::find_whole_word(haystack, term)
+        } else {
+            haystack.find(term)
+        }
+    }
+
+    // 在折叠文本中标出词项的所有出现位置，返回是否出现过
+    fn mark_term(&self, map: &FoldMap, term: &str, out: &mut Vec<[usize; 2]>) -> bool {
+        let starts: Vec<usize> = if self.options.whole_word {
+            matcher::whole_word_matches(&map.text, term).collect()
+        } else {
+            map.text.match_indices(term).map(|(i, _)| i).collect()
+        };
+        out.extend(starts.iter().map(|&i| map.original(i..i + term.len())));
+        !starts.is_empty()
+    }
+
+    /// 计算命中方式与高亮区间，判定规则与 `evaluate` 一致。
+    /// 比 `evaluate` 慢得多，只对要展示给用户的结果调用。
+    pub fn highlight(&self, name: &str, path: &str) -> Highlight {
+        let m = self.evaluate(name, path);
+        let name_map = FoldMap::new(name, |s| self.options.fold(s));
+        let path_map = self.options.match_path.then(|| FoldMap::new(path, |s| self.options.fold(s)));
+        let mut name_ranges = Vec::new();
+        let mut path_ranges = Vec::new();
+
+        // 直接出现的词项在文件名与路径中都标出
+        let mut literal = vec![false; self.terms.len()];
+        for (i, term) in self.terms.iter().enumerate() {
+            let in_name = self.mark_term(&name_map, term, &mut name_ranges);
+            let in_path = path_map.as_ref().is_some_and(|p| self.mark_term(p, term, &mut path_ranges));
+            literal[i] = in_name || in_path;
+        }
+
+        let kind = if m.exact_name {
+            MatchKind::Exact
+        } else if m.prefix {
+            MatchKind::Prefix
+        } else if !self.terms.is_empty() && literal.iter().all(|&l| l) {
+            MatchKind::Contains
+        } else if m.alias.is_some() || m.acronym || m.fuzzy.is_some() {
+            // 别名、缩写与模糊匹配始终不区分大小写
+            let ci_map = FoldMap::new(name, |s| self.options.fold_text(s, true));
+            if m.alias.is_some() {
+                // 每个经别名扩展的词项标出命中的第一个目标
+                for targets in &self.term_aliases_ci {
+                    if let Some(target) = targets.iter().find(|t| ci_map.text.contains(t.as_str())) {
+                        name_ranges.extend(ci_map.text.match_indices(target.as_str()).map(|(i, _)| ci_map.original(i..i + target.len())));
+                    }
+                }
+                MatchKind::Alias
+            } else if m.acronym {
+                name_ranges.extend(self.acronym_ranges(&ci_map));
+                MatchKind::Acronym
+            } else {
+                for (i, term) in self.terms_ci.iter().enumerate() {
+                    if !literal[i] {
+                        name_ranges.extend(fuzzy_ranges(&ci_map, term));
+                    }
+                }
+                MatchKind::Fuzzy
+            }
+        } else {
+            MatchKind::Partial
+        };
+
+        Highlight { kind, name_ranges: merge_ranges(name_ranges), path_ranges: merge_ranges(path_ranges) }
+    }
+
+    // 构成缩写的各单词首字母
+    fn acronym_ranges(&self, map: &FoldMap) -> Vec<[usize; 2]> {
+        let mut firsts: Vec<(usize, char)> = Vec::new();
+        let mut in_word = false;
+        for (i, c) in map.text.char_indices() {
+            let word_char = c.is_alphanumeric();
+            if word_char && !in_word {
+                firsts.push((i, c));
+            }
+            in_word = word_char;
+        }
+        let initials: String = firsts.iter().map(|&(_, c)| c).collect();
+        let Some(pos) = initials.find(&self.text_ci) else { return Vec::new() };
+        let start = initials[..pos].chars().count();
+        let len = self.text_ci.chars().count();
+        firsts[start..start + len].iter().map(|&(i, c)| map.original(i..i + c.len_utf8())).collect()
+    }
+
+    /// 判定单个文件的命中方式
+    pub fn evaluate(&self, name: &str, path: &str) -> EntryMatch {
+        self.evaluate_folded(name, path, None)
+    }
+
+    /// 同 `evaluate`，`folded` 为索引中预先折叠好的形式 (关闭简繁匹配时不适用，仍现场折叠)
+    pub fn evaluate_folded(&self, name: &str, path: &str, folded: Option<&Folded>) -> EntryMatch {
+        let mut m = EntryMatch::default();
+        let folded = folded.filter(|_| self.options.chinese_variants);
+        let name_ci = match folded {
+            Some(f) => Cow::Borrowed(f.name),
+            None => Cow::Owned(self.options.fold_text(name, true)),
+        };
+        let name_f = if self.options.case_sensitive { Cow::Owned(self.options.fold(name)) } else { Cow::Borrowed(name_ci.as_ref()) };
+        let path_f = match (self.options.match_path, folded) {
+            (false, _) => None,
+            (true, Some(f)) if !self.options.case_sensitive => Some(Cow::Borrowed(f.path)),
+            (true, _) => Some(Cow::Owned(self.options.fold(path))),
+        };
+        // 位图不覆盖词项的字符时必然不包含该词，省去子串查找
+        let may_contain = |i: usize, mask: Option<u64>| mask.is_none_or(|mask| self.term_masks[i] & !mask == 0);
+        let (name_mask, path_mask) = (folded.map(|f| f.name_mask), folded.map(|f| f.path_mask));
+
+        // 1. 多词 AND 匹配 (仿 Everything)，前 64 个词的命中位置记入位集供模糊兜底复用。
+        // 词项的别名目标出现在文件名中 (始终忽略大小写) 也算该词命中文件名
+        let mut in_name = 0;
+        let (mut name_hits, mut path_hits) = (0u64, 0u64);
+        let mut alias_rank: Option<usize> = None;
+        for (i, term) in self.terms.iter().enumerate() {
+            let rank = self.term_aliases_ci[i]
+                .iter()
+                .zip(&self.alias_masks[i])
+                .position(|(target, &mask)| name_mask.is_none_or(|n| mask & !n == 0) && name_ci.contains(target.as_str()));
+            if let Some(rank) = rank {
+                alias_rank = Some(alias_rank.unwrap_or(0) + rank);
+            }
+            let hit_name = rank.is_some() || (may_contain(i, name_mask) && self.contains_term(&name_f, term));
+            let hit_path = !hit_name && may_contain(i, path_mask) && path_f.as_ref().is_some_and(|p| self.contains_term(p, term));
+            if hit_name {
+                in_name += 1;
+            }
+            if hit_name || hit_path {
+                m.matched_terms += 1;
+            }
+            if i < 64 {
+                name_hits |= (hit_name as u64) << i;
+                path_hits |= (hit_path as u64) << i;
+            }
+        }
+        m.all_in_name = in_name == self.terms.len();
+        m.all_in_path = m.matched_terms == self.terms.len();
+        if m.all_in_path {
+            m.alias = alias_rank;
+        }
+
+        if m.all_in_name {
+            m.exact_name = name_f == self.text;
+            // 检查连续性：词项按输入顺序依次出现
+            let mut last_pos = 0;
+            m.continuous = self.terms.iter().all(|term| match self.find_term(&name_f[last_pos..], term) {
+                Some(pos) => {
+                    last_pos += pos + term.len();
+                    true
+                }
+                None => false,
+            });
+            m.prefix = self.terms.first().is_some_and(|t| name_f.starts_with(t.as_str()));
+        }
+
+        // 2. 缩写补充 (始终忽略大小写)
+        // 首字母必然出现在文件名中，位图不覆盖查询的字符时无需拼接首字母
+        let acronym_possible = name_mask.is_none_or(|mask| matcher::missing_chars(&self.text_ci, mask) == 0);
+        if !m.all_in_name && self.text_ci.len() >= 2 && acronym_possible {
+            let initials: String = name_ci
+                .split(|c: char| !c.is_alphanumeric())
+                .filter(|s| !s.is_empty())
+                .map(|s| s.chars().next().unwrap_or(' '))
+                .collect();
+            if initials.contains(&self.text_ci) {
+                m.acronym = true;
+                m.all_in_name = true;
+                m.all_in_path = true;
+            }
+        }
+
+        // 3. 模糊匹配兜底 (子序列 + 拼写容错)，每个词都要以某种方式命中。
+        // 模糊匹配只在忽略大小写且非全词时启用，此时 terms 与 terms_ci 一致，可复用第 1 步的命中情况
+        if self.options.fuzzy_enabled() && !m.is_full() {
+            let mut total = 0;
+            for (i, term) in self.terms_ci.iter().enumerate() {
+                let (hit_name, hit_path) = if i < 64 {
+                    (name_hits >> i & 1 == 1, path_hits >> i & 1 == 1)
+                } else {
+                    (name_ci.contains(term.as_str()), path_f.as_ref().is_some_and(|p| p.contains(term.as_str())))
+                };
+                if hit_name {
+                    total += 100;
+                } else if hit_path {
+                    total += 50;
+                } else {
+                    if name_mask.is_some_and(|mask| !matcher::may_fuzzy_match(term, mask)) {
+                        return m;
+                    }
+                    match matcher::fuzzy_word(term, &self.options.fuzzy_name(name), &name_ci) {
+                        Some(FuzzyKind::Subsequence(s)) => total += s,
+                        Some(FuzzyKind::Typo(d)) => total += 60 - (d as i32) * 20,
+                        None => return m,
+                    }
+                }
+            }
+            m.fuzzy = Some(total);
+        }
+
+        m
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(keyword: &str, options: SearchOptions) -> Query {
+        Query::new(keyword, options, &HashMap::new())
+    }
+
+    #[test]
+    fn name_only_ignores_parent_folders() {
+        let path = "/Users/me/test/report.pdf";
+        assert!(query("test", SearchOptions::default()).evaluate("report.pdf", path).is_full());
+        let options = SearchOptions { match_path: false, fuzzy: false, ..Default::default() };
+        assert!(!query("test", options).evaluate("report.pdf", path).is_full());
+    }
+
+    #[test]
+    fn case_sensitive_matching() {
+        let options = SearchOptions { case_sensitive: true, ..Default::default() };
+        assert!(query("README", options.clone()).evaluate("README.md", "/p/README.md").is_full());
+        let m = query("README", options).evaluate("readme.md", "/p/readme.md");
+        assert!(!m.is_full() && m.fuzzy.is_none());
+    }
+
+    #[test]
+    fn whole_word_matching() {
+        let options = SearchOptions { whole_word: true, ..Default::default() };
+        assert!(query("test", options.clone()).evaluate("unit test.rs", "/p/unit test.rs").is_full());
+        assert!(!query("test", options).evaluate("latest.rs", "/p/latest.rs").is_full());
+    }
+
+    #[test]
+    fn refinement_requires_longer_terms_and_same_options() {
+        let prev = query("repo", SearchOptions::default());
+        assert!(query("report", SearchOptions::default()).refines(&prev));
+        assert!(query("report repo", SearchOptions::default()).refines(&query("repo repo", SearchOptions::default())));
+        assert!(!query("rep", SearchOptions::default()).refines(&prev));
+        assert!(!query("report", SearchOptions { case_sensitive: true, ..Default::default() }).refines(&prev));
+        // 过短的旧词未参与模糊匹配，不能据此缩小
+        assert!(!query("report", SearchOptions::default()).refines(&query("r", SearchOptions::default())));
+    }
+
+    #[test]
+    fn refinement_keeps_typo_budget_and_rejects_added_terms() {
+        let strict = SearchOptions { fuzzy: false, ..Default::default() };
+        // 变长后容错额度增加 (3 -> 4 个字符)：abxd 能容错命中 abcd，却不在 abc 的候选中
+        let (prev, next) = (query("abc", SearchOptions::default()), query("abcd", SearchOptions::default()));
+        assert!(next.evaluate("abxd.txt", "/t/abxd.txt").fuzzy.is_some());
+        assert!(prev.evaluate("abxd.txt", "/t/abxd.txt").fuzzy.is_none());
+        assert!(!next.refines(&prev));
+        assert!(!query("abcdefgh", SearchOptions::default()).refines(&query("abcdefg", SearchOptions::default())));
+        assert!(query("abcdefg", SearchOptions::default()).refines(&query("abcd", SearchOptions::default())));
+        // 关闭模糊匹配时额度无关
+        assert!(query("abcd", strict.clone()).refines(&query("abc", strict.clone())));
+        // 在前面加字符：xabce 能容错命中 xabcd，却不在 abcd 的候选中
+        let (prev, next) = (query("abcd", SearchOptions::default()), query("xabcd", SearchOptions::default()));
+        assert!(next.evaluate("xabce.txt", "/t/xabce.txt").fuzzy.is_some());
+        let m = prev.evaluate("xabce.txt", "/t/xabce.txt");
+        assert!(!m.is_full() && m.fuzzy.is_none());
+        assert!(!next.refines(&prev));
+        assert!(query("xabcd", strict.clone()).refines(&query("abcd", strict.clone())));
+
+        // 新增的 AND 词可能带来只命中它的部分匹配
+        let (prev, next) = (query("report", strict.clone()), query("report 2024", strict.clone()));
+        let m = next.evaluate("2024.txt", "/t/2024.txt");
+        assert!(m.matched_terms > 0 && !m.is_full());
+        assert!(!next.refines(&prev));
+        assert!(!query("report 2024", SearchOptions::default()).refines(&query("report", SearchOptions::default())));
+    }
+
+    #[test]
+    fn alias_and_acronym_ignore_case_option() {
+        let mut mapping = HashMap::new();
+        mapping.insert("ps".to_string(), vec!["photoshop".to_string()]);
+        let options = SearchOptions { case_sensitive: true, ..Default::default() };
+        let q = Query::new("ps", options.clone(), &mapping);
+        assert_eq!(q.evaluate("Adobe Photoshop.app", "/Applications/Adobe Photoshop.app").alias, Some(0));
+        let q = Query::new("dpp", options, &mapping);
+        assert!(q.evaluate("Digital Photo Professional.app", "/Applications/Digital Photo Professional.app").acronym);
+    }
+
+    #[test]
+    fn alias_targets_are_tried_in_order() {
+        let mut mapping = HashMap::new();
+        mapping.insert("db".to_string(), vec!["doubao".to_string(), "douban".to_string()]);
+        let q = Query::new("DB", SearchOptions::default(), &mapping);
+        assert_eq!(q.evaluate("Doubao.app", "/Applications/Doubao.app").alias, Some(0));
+        assert_eq!(q.evaluate("豆瓣 Douban.app", "/Applications/豆瓣 Douban.app").alias, Some(1));
+        assert_eq!(q.evaluate("Dropbox.app", "/Applications/Dropbox.app").alias, None);
+        assert!(q.may_match(matcher::char_mask("douban.app"), 0));
+
+        // 目标变了不能在旧候选集中细化
+        let mut other = mapping.clone();
+        other.insert("db".to_string(), vec!["doubao".to_string()]);
+        assert!(!q.refines(&Query::new("db", SearchOptions::default(), &other)));
+    }
+
+    #[test]
+    fn quoted_phrases_are_single_terms() {
+        assert_eq!(split_terms(r#"ps "final cut" 海报"#), ["ps", "final cut", "海报"]);
+        assert_eq!(split_terms("“年度 总结”报告  \"未闭合 短语"), ["年度 总结", "报告", "未闭合 短语"]);
+        let q = query(r#""q3  report" PDF"#, SearchOptions::default());
+        assert_eq!((q.terms.as_slice(), q.text.as_str()), (["q3  report".to_string(), "pdf".to_string()].as_slice(), "q3  report pdf"));
+        assert!(q.evaluate("Q3  Report.pdf", "/docs/Q3  Report.pdf").is_full());
+        assert!(!q.evaluate("Q3 final report.pdf", "/docs/Q3 final report.pdf").is_full());
+    }
+
+    #[test]
+    fn aliases_expand_each_term() {
+        let mut mapping = HashMap::new();
+        mapping.insert("ps".to_string(), vec!["photoshop".to_string()]);
+        mapping.insert("wx".to_string(), vec!["wechat".to_string(), "weixin".to_string()]);
+        mapping.insert("after effects".to_string(), vec!["ae".to_string()]);
+        let q = |k: &str| Query::new(k, SearchOptions { fuzzy: false, ..Default::default() }, &mapping);
+
+        // 别名词之外的词仍需命中
+        let m = q("ps 海报").evaluate("海报 photoshop.png", "/design/海报 photoshop.png");
+        assert!(m.all_in_name && m.alias == Some(0));
+        let m = q("ps 海报").evaluate("photoshop.png", "/design/海报/photoshop.png");
+        assert!(!m.all_in_name && m.all_in_path && m.alias == Some(0));
+        let m = q("ps 海报").evaluate("photoshop.png", "/design/photoshop.png");
+        assert!(!m.is_full() && m.alias.is_none());
+        assert!(q("wx 文件").evaluate("weixin 文件.zip", "/tmp/weixin 文件.zip").alias == Some(1));
+        // 引号括起的短语整体查找别名
+        assert_eq!(q(r#""after effects" 模板"#).term_aliases, [vec!["ae".to_string()], vec![]]);
+        assert!(q("after effects").term_aliases.iter().all(Vec::is_empty));
+
+        let h = q("ps 海报").highlight("海报 Photoshop.png", "/design/海报 Photoshop.png");
+        assert_eq!((h.kind, h.name_ranges), (MatchKind::Alias, vec![[0, 2], [3, 12]]));
+        // 新增的别名词或普通词都可能带来旧候选集之外的命中，只加长已有的词才可以细化
+        assert!(!q("ps 海报").refines(&q("海报")));
+        assert!(!q("ps 海报").refines(&q("ps")));
+        assert!(q("ps 海报").refines(&q("ps 海")));
+    }
+
+    #[test]
+    fn highlight_reports_kind_and_char_ranges() {
+        let mut mapping = HashMap::new();
+        mapping.insert("ps".to_string(), vec!["photoshop".to_string()]);
+        let q = |k: &str| Query::new(k, SearchOptions::default(), &mapping);
+
+        // NFD 文件名：区间按原文字符计，é 占两个字符
+        let h = q("résumé").highlight("Re\u{301}sume\u{301} 2024.pdf", "/Users/me/Re\u{301}sume\u{301} 2024.pdf");
+        assert_eq!(h.kind, MatchKind::Prefix);
+        assert_eq!(h.name_ranges, vec![[0, 8]]);
+        assert_eq!(h.path_ranges, vec![[10, 18]]);
+
+        let h = q("2024 report").highlight("report 2024.pdf", "/docs/report 2024.pdf");
+        assert_eq!(h.kind, MatchKind::Contains);
+        assert_eq!(h.name_ranges, vec![[0, 6], [7, 11]]);
+
+        let h = q("ps").highlight("Adobe Photoshop.app", "/Applications/Adobe Photoshop.app");
+        assert_eq!((h.kind, h.name_ranges), (MatchKind::Alias, vec![[6, 15]]));
+
+        let h = q("dpp").highlight("Digital Photo Professional.app", "/Applications/Digital Photo Professional.app");
+        assert_eq!((h.kind, h.name_ranges), (MatchKind::Acronym, vec![[0, 1], [8, 9], [14, 15]]));
+
+        let h = q("phtshp").highlight("Photoshop.app", "/Applications/Photoshop.app");
+        assert_eq!((h.kind, h.name_ranges), (MatchKind::Fuzzy, vec![[0, 2], [3, 4], [5, 7], [8, 9]]));
+
+        let h = q("photoshpo").highlight("Photoshop.app", "/Applications/Photoshop.app");
+        assert_eq!((h.kind, h.name_ranges), (MatchKind::Fuzzy, vec![[0, 9]]));
+    }
+}
@@ -0,0 +1,422 @@
+// 相关度打分：基础匹配分 + 程序类加成 + 点击历史 + 路径深度与位置
+//
+// 同一套规则既用于打分，也能逐条列出每条规则的贡献 (explain)，用于排查「为什么它排在前面」。
+// 规则名是稳定的英文标识，前端与 CLI 的 JSON 输出直接使用。
+//
+// 各项权重与自定义路径规则来自排序配置 (ranking.json)，可定义多套方案并按搜索类型切换：
+//
+//   {
+//     "profiles": {
+//       "default": { "rules": [{ "path": "~/Work", "points": 3000 }, { "path": "~/Downloads/old", "points": -8000 }] },
+//       "apps": { "weights": { "applications": 8000, "depth": 0 }, "clickHalfLifeDays": 90 }
+//     },
+//     "filterProfiles": { "app": "apps" }
+//   }
+//
+// 未写出的权重取内置默认值；未配置文件时所有类型都使用内置方案。
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::history::{self, ClickHistory};
+use crate::query::Query;
+use crate::SearchResult;
+
+/// 单条规则对得分的贡献
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct ScoreRule {
+    pub rule: &'static str,
+    pub points: i32,
+    /// 自定义路径规则所匹配的路径
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+/// 各排序规则的分值
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(default, rename_all = "camelCase")]
+pub struct RankingWeights {
+    /// 文件名与查询完全相同
+    pub exact_name: i32,
+    /// 通过别名命中
+    pub alias: i32,
+    /// 别名有多个目标时，命中第二个及之后的目标每靠后一位的分值
+    pub alias_order: i32,
+    /// 通过首字母缩写命中
+    pub acronym: i32,
+    /// 所有词项按顺序出现在文件名中
+    pub continuous_in_name: i32,
+    /// 在连续命中的基础上，文件名以第一个词项开头
+    pub prefix: i32,
+    /// 所有词项出现在文件名中，但不连续
+    pub all_in_name: i32,
+    /// 所有词项出现在完整路径中
+    pub all_in_path: i32,
+    /// 模糊命中的基础分，另加封顶为 `fuzzy_max` 的模糊得分
+    pub fuzzy: i32,
+    pub fuzzy_max: i32,
+    /// 按程序搜索时的 .app 程序包
+    pub app_bundle: i32,
+    /// 每次刚发生的点击 (不论查询)，随时间按半衰期衰减
+    pub clicks: i32,
+    /// 在当前查询 (或以它开头的查询) 下选中过该结果，每次同样按半衰期衰减
+    pub learned: i32,
+    /// 嵌套在 .app 包内部的文件
+    pub inside_app_bundle: i32,
+    /// 每级路径深度 (/Applications 下除外)
+    pub depth: i32,
+    /// 位于 /Applications
+    pub applications: i32,
+    /// 位于桌面
+    pub desktop: i32,
+}
+
+impl Default for RankingWeights {
+    fn default() -> Self {
+        Self {
+            exact_name: 20000, // 进一步提高权重，确保绝对置顶
+            alias: 20000,
+            alias_order: -2000, // 小于一次按查询学习的加成，点击后可改变目标的先后
+            acronym: 20000,
+            continuous_in_name: 10000,
+            prefix: 5000, // 增加开头匹配加成
+            all_in_name: 5000,
+            all_in_path: 2000,
+            fuzzy: 1000,
+            fuzzy_max: 900,
+            app_bundle: 10000, // 确保 .app 目录排在其他文件前面
+            clicks: 1000, // 全局点击只作轻微加成，主要靠按查询学习
+            learned: 15000,
+            inside_app_bundle: -10000,
+            depth: -50,
+            applications: 5000, // 提高应用目录基础分
+            desktop: 1000,
+        }
+    }
+}
+
+/// 自定义路径规则：位于 `path` (可用 ~ 表示主目录) 之下的结果加上 `points`，负数即降权
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub struct PathRule {
+    pub path: String,
+    pub points: i32,
+}
+
+impl PathRule {
+    fn matches(&self, path: &str) -> bool {
+        path.strip_prefix(self.path.as_str()).is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+    }
+}
+
+/// 一套排序方案
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(default, rename_all = "camelCase")]
+pub struct RankingProfile {
+    pub weights: RankingWeights,
+    pub rules: Vec<PathRule>,
+    /// 点击加成的半衰期 (天)，0 表示不衰减
+    pub click_half_life_days: f64,
+}
+
+impl Default for RankingProfile {
+    fn default() -> Self {
+        Self { weights: RankingWeights::default(), rules: Vec::new(), click_half_life_days: 30.0 }
+    }
+}
+
+// ranking.json 的内容
+#[derive(Deserialize, Default)]
+#[serde(default, rename_all = "camelCase")]
+struct RankingFile {
+    profiles: HashMap<String, RankingProfile>,
+    filter_profiles: HashMap<String, String>,
+}
+
+/// 排序配置：命名的排序方案，以及各搜索类型使用的方案
+#[derive(Clone, Debug, Default)]
+pub struct RankingConfig {
+    profiles: HashMap<String, Arc<RankingProfile>>,
+    /// 搜索类型 -> 方案名，未列出的类型使用 default 方案
+    filter_profiles: HashMap<String, String>,
+}
+
+/// 默认方案的名称
+pub const DEFAULT_PROFILE: &str = "default";
+
+impl RankingConfig {
+    /// 解析配置并展开路径规则中的 ~ (取不到用户目录时这些规则不生效)；引用了不存在的方案时报错
+    pub fn parse(content: &str, home: Option<&str>) -> Result<Self, String> {
+        let mut file: RankingFile = serde_json::from_str(content).map_err(|e| format!("排序配置格式错误: {}", e))?;
+        for (filter, name) in &file.filter_profiles {
+            if name != DEFAULT_PROFILE && !file.profiles.contains_key(name) {
+                return Err(format!("搜索类型 {} 引用了不存在的排序方案: {}", filter, name));
+            }
+        }
+        for profile in file.profiles.values_mut() {
+            for rule in &mut profile.rules {
+                if let (Some(rest), Some(home)) = (rule.path.strip_prefix('~'), home) {
+                    rule.path = format!("{}{}", home.trim_end_matches('/'), rest);
+                }
+                while rule.path.len() > 1 && rule.path.ends_with('/') {
+                    rule.path.pop();
+                }
+            }
+        }
+        Ok(Self {
+            profiles: file.profiles.into_iter().map(|(name, profile)| (name, Arc::new(profile))).collect(),
+            filter_profiles: file.filter_profiles,
+        })
+    }
+
+    /// 读取配置文件；文件不存在时使用内置方案
+    pub fn load(path: &Path, home: Option<&str>) -> Result<Self, String> {
+        match std::fs::read_to_string(path) {
+            Ok(content) => Self::parse(&content, home),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(format!("读取排序配置失败: {}", e)),
+        }
+    }
+
+    /// 某个搜索类型使用的排序方案
+    pub fn profile_for(&self, filter_type: &str) -> Arc<RankingProfile> {
+        let name = self.filter_profiles.get(filter_type).map_or(DEFAULT_PROFILE, String::as_str);
+        self.profiles
+            .get(name)
+            .or_else(|| self.profiles.get(DEFAULT_PROFILE))
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+/// 得分明细，`rules` 按规则生效的顺序排列，各项之和为 `total`
+#[derive(Serialize, Clone, Debug)]
+pub struct ScoreExplanation {
+    pub total: i32,
+    pub rules: Vec<ScoreRule>,
+}
+
+// 累计得分，需要明细时顺带记录每条规则
+struct Tally {
+    total: i32,
+    rules: Option<Vec<ScoreRule>>,
+}
+
+impl Tally {
+    fn add(&mut self, rule: &'static str, points: i32) {
+        self.add_detail(rule, points, None);
+    }
+
+    fn add_detail(&mut self, rule: &'static str, points: i32, detail: Option<&str>) {
+        // 分值为 0 的规则 (如配置中关闭的项) 不计入明细
+        if points == 0 {
+            return;
+        }
+        self.total += points;
+        if let Some(rules) = self.rules.as_mut() {
+            rules.push(ScoreRule { rule, points, detail: detail.map(str::to_string) });
+        }
+    }
+}
+
+fn tally(res: &SearchResult, query: &Query, filter_type: &str, history: &ClickHistory, profile: &RankingProfile, tally: &mut Tally) {
+    let m = query.evaluate(&res.name, &res.path);
+    let w = &profile.weights;
+
+    // A. 基础匹配权重 (智能多词加权)
+    if m.all_in_name {
+        if m.exact_name {
+            tally.add("exactName", w.exact_name);
+        } else if let Some(rank) = m.alias {
+            tally.add("alias", w.alias);
+            tally.add("aliasOrder", rank as i32 * w.alias_order);
+        } else if m.acronym {
+            tally.add("acronym", w.acronym);
+        } else if m.continuous {
+            tally.add("continuousInName", w.continuous_in_name);
+            if m.prefix {
+                tally.add("prefix", w.prefix);
+            }
+        } else {
+            tally.add("allInName", w.all_in_name);
+        }
+    } else if m.all_in_path {
+        tally.add("allInPath", w.all_in_path);
+    } else if let Some(fuzzy_score) = m.fuzzy {
+        // 模糊命中：子序列得分越高越靠前，拼写容错次之，整体低于子串匹配
+        tally.add("fuzzy", w.fuzzy + fuzzy_score.clamp(0, w.fuzzy_max.max(0)));
+    }
+
+    // 针对程序类的特殊加成
+    if filter_type == "app"
+        && (res.path.ends_with(".app") || res.path.ends_with(".app/")) {
+        tally.add("appBundle", w.app_bundle);
+    }
+
+    // B. 点击历史加成，越久以前的点击分量越轻
+    let (half_life, now) = (profile.click_half_life_days * 86400.0, history::unix_now());
+    let clicks = history.frecency(&res.path, half_life, now);
+    if clicks > 0.0 {
+        tally.add("clicks", (clicks * w.clicks as f64).round() as i32);
+    }
+    // 在同一查询下被选中过的结果 (权重最高，体现自学习)
+    let learned = history.learned(&query.learning_key, &res.path, half_life, now);
+    if learned > 0.0 {
+        tally.add("learned", (learned * w.learned as f64).round() as i32);
+    }
+
+    // C. 路径深度与嵌套惩罚
+    let depth = res.path.split('/').count() as i32;
+
+    // 惩罚嵌套在 .app 包内部的子程序 (如 Digital Photo Professional 4.app/Contents/Resources/...)
+    if res.path.contains(".app/Contents/") {
+        tally.add("insideAppBundle", w.inside_app_bundle);
+    }
+
+    if !res.path.starts_with("/Applications") {
+        tally.add("depth", depth * w.depth);
+    }
+
+    // D. 位置权重
+    if res.path.starts_with("/Applications") {
+        tally.add("applications", w.applications);
+    } else if res.path.contains("/Desktop") {
+        tally.add("desktop", w.desktop);
+    }
+
+    // E. 自定义路径规则，可叠加
+    for rule in profile.rules.iter().filter(|r| r.matches(&res.path)) {
+        tally.add_detail("pathRule", rule.points, Some(&rule.path));
+    }
+}
+
+/// 计算并写入结果的相关度得分
+pub fn rank_result(res: &mut SearchResult, query: &Query, filter_type: &str, history: &ClickHistory, profile: &RankingProfile) {
+    let mut t = Tally { total: 0, rules: None };
+    tally(res, query, filter_type, history, profile, &mut t);
+    res.score = t.total;
+}
+
+/// 逐条列出得分构成，与 `rank_result` 的结果一致
+pub fn explain(res: &SearchResult, query: &Query, filter_type: &str, history: &ClickHistory, profile: &RankingProfile) -> ScoreExplanation {
+    let mut t = Tally { total: 0, rules: Some(Vec::new()) };
+    tally(res, query, filter_type, history, profile, &mut t);
+    ScoreExplanation { total: t.total, rules: t.rules.unwrap_or_default() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::SearchOptions;
+    use crate::history::HistoryOp;
+
+    fn result(path: &str) -> SearchResult {
+        let name = path.rsplit('/').next().unwrap_or(path).to_string();
+        SearchResult { path: path.to_string(), name, score: 0, highlight: None, explain: None }
+    }
+
+    #[test]
+    fn explanation_adds_up_to_score() {
+        let query = Query::new("report", SearchOptions::default(), &HashMap::new());
+        let mut history = ClickHistory::default();
+        history.record("/Users/me/Desktop/report 2024.pdf", history::unix_now());
+        history.record("/Users/me/Desktop/report 2024.pdf", history::unix_now());
+
+        let mut res = result("/Users/me/Desktop/report 2024.pdf");
+        let profile = RankingProfile::default();
+        rank_result(&mut res, &query, "all", &history, &profile);
+        let explanation = explain(&res, &query, "all", &history, &profile);
+        assert_eq!(explanation.total, res.score);
+        assert_eq!(explanation.rules.iter().map(|r| r.points).sum::<i32>(), res.score);
+        let rules: Vec<&str> = explanation.rules.iter().map(|r| r.rule).collect();
+        assert_eq!(rules, ["continuousInName", "prefix", "clicks", "depth", "desktop"]);
+        assert_eq!(explanation.rules[2], ScoreRule { rule: "clicks", points: 2000, detail: None });
+        assert_eq!(explanation.rules[3], ScoreRule { rule: "depth", points: -250, detail: None });
+    }
+
+    #[test]
+    fn learned_choice_only_boosts_its_query() {
+        let mut history = ClickHistory::default();
+        let chosen = "/Users/me/Documents/reports/q3 report.pdf";
+        let now = history::unix_now();
+        history.apply(&HistoryOp::open(chosen, Some(&crate::query::learning_key("Report")), now));
+        let profile = RankingProfile::default();
+        let score = |keyword: &str, path: &str| {
+            let query = Query::new(keyword, SearchOptions::default(), &HashMap::new());
+            let mut res = result(path);
+            rank_result(&mut res, &query, "all", &history, &profile);
+            res.score
+        };
+
+        // 输入 rep 时学到的结果压过文件名更贴切的候选
+        assert!(score("rep", chosen) > score("rep", "/Users/me/report.pdf"));
+        let query = Query::new("rep", SearchOptions::default(), &HashMap::new());
+        let explanation = explain(&result(chosen), &query, "all", &history, &profile);
+        assert!(explanation.rules.contains(&ScoreRule { rule: "learned", points: profile.weights.learned, detail: None }));
+        // 其它查询只得到普通打开次数的加分
+        let mut fresh = ClickHistory::default();
+        fresh.apply(&HistoryOp::open(chosen, None, now));
+        let query = Query::new("q3", SearchOptions::default(), &HashMap::new());
+        let (mut a, mut b) = (result(chosen), result(chosen));
+        rank_result(&mut a, &query, "all", &history, &profile);
+        rank_result(&mut b, &query, "all", &fresh, &profile);
+        assert_eq!(a.score, b.score);
+    }
+
+    #[test]
+    fn earlier_alias_targets_rank_higher_until_learned() {
+        let mapping = HashMap::from([("db".to_string(), vec!["doubao".to_string(), "douban".to_string()])]);
+        let query = Query::new("db", SearchOptions::default(), &mapping);
+        let profile = RankingProfile::default();
+        let score = |history: &ClickHistory, path: &str| {
+            let mut res = result(path);
+            rank_result(&mut res, &query, "app", history, &profile);
+            res.score
+        };
+        let (doubao, douban) = ("/Applications/Doubao.app", "/Applications/Douban.app");
+
+        let mut history = ClickHistory::default();
+        assert!(score(&history, doubao) > score(&history, douban));
+        let explanation = explain(&result(douban), &query, "app", &history, &profile);
+        assert!(explanation.rules.contains(&ScoreRule { rule: "aliasOrder", points: -2000, detail: None }));
+
+        // 在 db 下选过豆瓣后，豆瓣排到前面
+        history.apply(&HistoryOp::open(douban, Some(&query.learning_key), history::unix_now()));
+        assert!(score(&history, douban) > score(&history, doubao));
+    }
+
+    #[test]
+    fn profiles_switch_per_filter_type() {
+        let config = RankingConfig::parse(
+            r#"{
+                "profiles": {
+                    "default": { "rules": [{ "path": "~/Work/", "points": 3000 }, { "path": "~/Downloads/old", "points": -8000 }] },
+                    "apps": { "weights": { "applications": 8000, "depth": 0 } }
+                },
+                "filterProfiles": { "app": "apps" }
+            }"#,
+            Some("/Users/me"),
+        )
+        .unwrap();
+        let query = Query::new("notes", SearchOptions::default(), &HashMap::new());
+        let history = ClickHistory::default();
+        let score = |path: &str, filter_type: &str| {
+            let mut res = result(path);
+            rank_result(&mut res, &query, filter_type, &history, &config.profile_for(filter_type));
+            res.score
+        };
+
+        // 路径规则按目录边界匹配，~ 展开为主目录
+        assert_eq!(score("/Users/me/Work/notes.txt", "all") - score("/Users/me/Worker/notes.txt", "all"), 3000);
+        assert_eq!(score("/Users/me/Downloads/old/notes.txt", "all") - score("/Users/me/Downloads/new/notes.txt", "all"), -8000);
+        // app 类型使用 apps 方案：未写出的权重保持默认，路径规则不继承
+        let apps = config.profile_for("app");
+        assert_eq!(apps.weights.applications, 8000);
+        assert_eq!(apps.weights.clicks, RankingWeights::default().clicks);
+        let defaults = RankingWeights::default();
+        assert_eq!(score("/Users/me/Work/notes.txt", "app"), defaults.continuous_in_name + defaults.prefix);
+
+        assert!(RankingConfig::parse(r#"{ "filterProfiles": { "app": "missing" } }"#, Some("/Users/me")).is_err());
+    }
+}
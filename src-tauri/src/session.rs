@@ -0,0 +1,184 @@
+// 搜索会话：每个窗口一个会话，新查询到来时取消同一窗口上一次仍在进行的搜索
+//
+// 前端每次输入都会带上递增的查询序号 (seq)，后端据此判断哪个查询是最新的：
+// 更新的序号会取消旧查询 (包括杀掉其 mdfind 子进程)，落后的序号直接视为过期。
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+
+use crate::query::Query;
+
+/// 协作式取消标记，搜索各阶段自行检查或等待
+#[derive(Default)]
+pub struct CancelToken {
+    cancelled: AtomicBool,
+    notify: Notify,
+}
+
+impl CancelToken {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+        self.notify.notify_waiters();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// 等待直到被取消，用于与子进程输出做 select
+    pub async fn cancelled(&self) {
+        loop {
+            // 先注册等待再检查标记，避免错过 cancel 时的唤醒
+            let notified = self.notify.notified();
+            if self.is_cancelled() {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+/// 上一次内存扫描的候选集 (索引下标)，新查询是其细化时只需复查这些条目
+#[derive(Clone)]
+pub struct ScanCache {
+    pub query: Query,
+    pub filter_type: String,
+    /// 扫描时的索引版本，索引重建后下标失效
+    pub generation: u64,
+    pub candidates: Arc<Vec<usize>>,
+}
+
+struct Session {
+    seq: u64,
+    token: Arc<CancelToken>,
+    last_scan: Option<ScanCache>,
+}
+
+/// 按窗口 label 管理的搜索会话表
+#[derive(Clone, Default)]
+pub struct SearchSessions {
+    inner: Arc<Mutex<HashMap<String, Session>>>,
+}
+
+impl SearchSessions {
+    /// 登记一次查询并返回其序号与取消标记。
+    /// - 序号比当前新：取消上一次查询，开启新会话
+    /// - 序号与当前相同 (如同一查询翻页)：共用当前的取消标记
+    /// - 序号落后：查询已过期，返回 None
+    /// - 未提供序号：自动分配下一个序号
+    pub fn begin(&self, window: &str, seq: Option<u64>) -> Option<(u64, Arc<CancelToken>)> {
+        let mut sessions = self.inner.lock().unwrap();
+        let current = sessions.get(window).map(|s| s.seq);
+        let seq = seq.unwrap_or_else(|| current.map_or(1, |c| c + 1));
+
+        if let Some(session) = sessions.get(window) {
+            if seq < session.seq {
+                return None;
+            }
+            if seq == session.seq {
+                return Some((seq, session.token.clone()));
+            }
+            session.token.cancel();
+        }
+
+        let token = CancelToken::new();
+        let last_scan = sessions.remove(window).and_then(|s| s.last_scan);
+        sessions.insert(window.to_string(), Session { seq, token: token.clone(), last_scan });
+        Some((seq, token))
+    }
+
+    /// 取出该窗口上一次完成的扫描候选集，仅当新查询是其细化 (词在末尾加长、类型更窄；新增 AND 词不算)
+    /// 且索引未重建时返回，否则调用方应全量扫描
+    pub fn cached_scan(&self, window: &str, query: &Query, filter_type: &str, generation: u64) -> Option<ScanCache> {
+        let sessions = self.inner.lock().unwrap();
+        let scan = sessions.get(window)?.last_scan.as_ref()?;
+        let usable = scan.generation == generation
+            && (scan.filter_type == "all" || scan.filter_type == filter_type)
+            && query.refines(&scan.query);
+        usable.then(|| scan.clone())
+    }
+
+    /// 保存扫描候选集；查询已被取代时丢弃，避免旧结果覆盖新结果
+    pub fn store_scan(&self, window: &str, seq: u64, scan: ScanCache) {
+        if let Some(session) = self.inner.lock().unwrap().get_mut(window) {
+            if session.seq == seq {
+                session.last_scan = Some(scan);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::SearchOptions;
+
+    fn query(keyword: &str) -> Query {
+        Query::new(keyword, SearchOptions::default(), &HashMap::new())
+    }
+
+    #[test]
+    fn newer_seq_cancels_and_stale_seq_is_rejected() {
+        let sessions = SearchSessions::default();
+        let (seq, first) = sessions.begin("main", Some(3)).unwrap();
+        assert_eq!(seq, 3);
+        // 同一序号 (翻页) 共用取消标记
+        let (_, same) = sessions.begin("main", Some(3)).unwrap();
+        assert!(Arc::ptr_eq(&first, &same));
+        assert!(!first.is_cancelled());
+
+        let (_, second) = sessions.begin("main", Some(4)).unwrap();
+        assert!(first.is_cancelled() && !second.is_cancelled());
+        assert!(sessions.begin("main", Some(2)).is_none());
+        assert!(!second.is_cancelled());
+        // 未带序号时自动递增；其它窗口互不影响
+        assert_eq!(sessions.begin("main", None).unwrap().0, 5);
+        assert!(second.is_cancelled());
+        assert_eq!(sessions.begin("other", Some(1)).unwrap().0, 1);
+    }
+
+    #[tokio::test]
+    async fn cancel_wakes_waiters() {
+        let token = CancelToken::new();
+        let waiter = tokio::spawn({
+            let token = token.clone();
+            async move { token.cancelled().await }
+        });
+        tokio::task::yield_now().await;
+        token.cancel();
+        waiter.await.unwrap();
+        // 已取消的标记立即返回
+        token.cancelled().await;
+    }
+
+    #[test]
+    fn cached_scan_is_reused_only_for_refinements() {
+        let sessions = SearchSessions::default();
+        let (seq, _) = sessions.begin("main", Some(1)).unwrap();
+        let scan = ScanCache { query: query("phot"), filter_type: "all".into(), generation: 7, candidates: Arc::new(vec![1, 2, 3]) };
+        sessions.store_scan("main", seq, scan);
+        sessions.begin("main", Some(2)).unwrap();
+
+        let cached = sessions.cached_scan("main", &query("photo"), "image", 7).unwrap();
+        assert_eq!(*cached.candidates, [1, 2, 3]);
+        assert!(sessions.cached_scan("main", &query("pho"), "all", 7).is_none());
+        assert!(sessions.cached_scan("main", &query("photo"), "all", 8).is_none());
+        assert!(sessions.cached_scan("other", &query("photo"), "all", 7).is_none());
+
+        // 已被取代的查询不能覆盖候选集
+        let stale = ScanCache { query: query("x"), filter_type: "all".into(), generation: 7, candidates: Arc::new(Vec::new()) };
+        sessions.store_scan("main", 1, stale);
+        assert!(sessions.cached_scan("main", &query("photo"), "all", 7).is_some());
+        // 类型更宽时不能复用
+        let (seq, _) = sessions.begin("main", Some(3)).unwrap();
+        sessions.store_scan("main", seq, ScanCache { query: query("phot"), filter_type: "image".into(), generation: 7, candidates: Arc::new(vec![1]) });
+        assert!(sessions.cached_scan("main", &query("photo"), "all", 7).is_none());
+        assert!(sessions.cached_scan("main", &query("photo"), "image", 7).is_some());
+    }
+}
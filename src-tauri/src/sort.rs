@@ -0,0 +1,179 @@
+// 结果排序：除相关度外还可按名称、路径、扩展名、大小、日期或点击次数排序，像文件列表一样浏览
+//
+// 平手时依次按相关度、路径决出，同一查询反复翻页得到的顺序完全一致。
+// 取不到属性的结果 (如文件已被删除) 无论升降序都排在最后。
+
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::time::SystemTime;
+
+use crate::history::ClickHistory;
+use crate::matcher;
+use crate::SearchResult;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum SortKey {
+    #[default]
+    Relevance,
+    Name,
+    Path,
+    Extension,
+    Size,
+    Modified,
+    Created,
+    Clicks,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum SortDirection {
+    Asc,
+    Desc,
+}
+
+/// 排序方式，前端与 CLI 均可指定
+#[derive(Serialize, Deserialize, Clone, Copy, Default, PartialEq, Debug)]
+#[serde(default, rename_all = "camelCase")]
+pub struct SortOrder {
+    pub key: SortKey,
+    /// 不指定时使用排序键的默认方向：相关度、大小、日期、点击次数从大到小，其余从小到大
+    pub direction: Option<SortDirection>,
+}
+
+impl SortKey {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s.to_ascii_lowercase().as_str() {
+            "relevance" | "score" => Ok(Self::Relevance),
+            "name" => Ok(Self::Name),
+            "path" => Ok(Self::Path),
+            "extension" | "ext" => Ok(Self::Extension),
+            "size" => Ok(Self::Size),
+            "modified" | "mtime" => Ok(Self::Modified),
+            "created" | "ctime" => Ok(Self::Created),
+            "clicks" => Ok(Self::Clicks),
+            _ => Err(format!("未知的排序方式: {} (可选 relevance, name, path, extension, size, modified, created, clicks)", s)),
+        }
+    }
+
+    fn default_direction(self) -> SortDirection {
+        match self {
+            Self::Relevance | Self::Size | Self::Modified | Self::Created | Self::Clicks => SortDirection::Desc,
+            Self::Name | Self::Path | Self::Extension => SortDirection::Asc,
+        }
+    }
+}
+
+impl SortDirection {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s.to_ascii_lowercase().as_str() {
+            "asc" => Ok(Self::Asc),
+            "desc" => Ok(Self::Desc),
+            _ => Err(format!("未知的排序方向: {} (可选 asc, desc)", s)),
+        }
+    }
+}
+
+// 单条结果的排序值，同一次排序中所有值都是同一种
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+enum SortValue {
+    Text(String),
+    Number(i64),
+    Time(SystemTime),
+}
+
+fn extension(name: &str) -> String {
+    // 以点开头的隐藏文件 (如 .zshrc) 没有扩展名
+    match name.rfind('.') {
+        Some(i) if i > 0 => matcher::fold(&name[i + 1..]),
+        _ => String::new(),
+    }
+}
+
+fn sort_value(res: &SearchResult, key: SortKey, history: &ClickHistory) -> Option<SortValue> {
+    match key {
+        SortKey::Relevance => Some(SortValue::Number(res.score as i64)),
+        SortKey::Name => Some(SortValue::Text(matcher::fold(&res.name))),
+        SortKey::Path => Some(SortValue::Text(matcher::fold(&res.path))),
+        SortKey::Extension => Some(SortValue::Text(extension(&res.name))),
+        SortKey::Size => std::fs::metadata(&res.path).ok().map(|m| SortValue::Number(m.len() as i64)),
+        SortKey::Modified => std::fs::metadata(&res.path).and_then(|m| m.modified()).ok().map(SortValue::Time),
+        SortKey::Created => std::fs::metadata(&res.path).and_then(|m| m.created()).ok().map(SortValue::Time),
+        SortKey::Clicks => Some(SortValue::Number(history.total(&res.path) as i64)),
+    }
+}
+
+/// 按指定方式排序 (结果的 `score` 须已计算)
+pub fn sort_results(results: Vec<SearchResult>, order: SortOrder, history: &ClickHistory) -> Vec<SearchResult> {
+    let descending = order.direction.unwrap_or(order.key.default_direction()) == SortDirection::Desc;
+    let mut keyed: Vec<(Option<SortValue>, SearchResult)> = results
+        .into_iter()
+        .map(|r| (sort_value(&r, order.key, history), r))
+        .collect();
+
+    keyed.sort_by(|(ka, a), (kb, b)| {
+        let primary = match (ka, kb) {
+            (Some(x), Some(y)) if descending => y.cmp(x),
+            (Some(x), Some(y)) => x.cmp(y),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        };
+        primary
+            .then_with(|| b.score.cmp(&a.score))
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    keyed.into_iter().map(|(_, r)| r).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(path: &str, score: i32) -> SearchResult {
+        let name = path.rsplit('/').next().unwrap_or(path).to_string();
+        SearchResult { path: path.to_string(), name, score, highlight: None, explain: None }
+    }
+
+    fn paths(results: &[SearchResult]) -> Vec<&str> {
+        results.iter().map(|r| r.path.as_str()).collect()
+    }
+
+    #[test]
+    fn relevance_ties_break_by_path() {
+        let results = vec![result("/b/x.txt", 10), result("/a/x.txt", 10), result("/c/y.txt", 20)];
+        let sorted = sort_results(results, SortOrder::default(), &ClickHistory::default());
+        assert_eq!(paths(&sorted), ["/c/y.txt", "/a/x.txt", "/b/x.txt"]);
+    }
+
+    #[test]
+    fn sort_by_name_and_extension() {
+        let results = vec![result("/a/beta.PDF", 0), result("/b/Alpha.txt", 0), result("/c/.zshrc", 0)];
+        let order = SortOrder { key: SortKey::Name, direction: None };
+        assert_eq!(paths(&sort_results(results.clone(), order, &ClickHistory::default())), ["/c/.zshrc", "/b/Alpha.txt", "/a/beta.PDF"]);
+        let order = SortOrder { key: SortKey::Extension, direction: Some(SortDirection::Desc) };
+        assert_eq!(paths(&sort_results(results, order, &ClickHistory::default())), ["/b/Alpha.txt", "/a/beta.PDF", "/c/.zshrc"]);
+    }
+
+    #[test]
+    fn missing_metadata_sorts_last() {
+        let dir = std::env::temp_dir();
+        let existing = dir.join(format!("xtap-sort-test-{}.txt", std::process::id()));
+        std::fs::write(&existing, b"hello").unwrap();
+        let existing = existing.to_string_lossy().to_string();
+        let results = vec![result("/nonexistent/xtap/missing.txt", 0), result(&existing, 0)];
+        for direction in [SortDirection::Asc, SortDirection::Desc] {
+            let order = SortOrder { key: SortKey::Size, direction: Some(direction) };
+            let sorted = sort_results(results.clone(), order, &ClickHistory::default());
+            assert_eq!(sorted[0].path, existing);
+        }
+        let _ = std::fs::remove_file(&existing);
+    }
+
+    #[test]
+    fn parse_keys() {
+        assert_eq!(SortKey::parse("Modified"), Ok(SortKey::Modified));
+        assert_eq!(SortDirection::parse("desc"), Ok(SortDirection::Desc));
+        assert!(SortKey::parse("color").is_err());
+    }
+}
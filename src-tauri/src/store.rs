@@ -0,0 +1,638 @@
+// 内嵌 SQLite 数据库：点击/打开记录、按查询学习的选择、设置、保存的搜索与各扫描根目录的索引信息
+//
+// 表结构用 PRAGMA user_version 记录版本，启动时按顺序执行尚未应用的迁移。
+// 早期版本的 click_history.json 在数据库首次创建时导入，导入后删除。
+// 数据库用 SQLCipher 整库加密 (密钥见 crypto.rs)；旧版本的明文数据库在打开时就地加密，
+// 无法用当前密钥解开的数据库 (密钥丢失或文件损坏) 改名为 store.db.unreadable-<时间> 保留，另建新库。
+// 文件索引快照 (index.cache) 有数百万条且随时可以重新扫描生成，仍作为缓存文件单独存放，
+// 数据库只记录每个扫描根目录的条目数与扫描时间。
+
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Mutex;
+
+use crate::history::{ClickHistory, HistoryOp, MAX_OPENS};
+use crate::paths::with_suffix;
+use crate::query::SearchOptions;
+use crate::sort::SortOrder;
+
+/// 按顺序执行的迁移，第 N 条把版本从 N 升到 N + 1
+const MIGRATIONS: &[&str] = &[
+    // 1: 初始表结构
+    "CREATE TABLE opens (path TEXT NOT NULL, opened_at INTEGER NOT NULL);
+     CREATE INDEX opens_path ON opens (path);
+     CREATE TABLE query_choices (prefix TEXT NOT NULL, path TEXT NOT NULL, opened_at INTEGER NOT NULL);
+     CREATE INDEX query_choices_path ON query_choices (path);
+     CREATE TABLE settings (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+     CREATE TABLE saved_searches (
+         name TEXT PRIMARY KEY,
+         keyword TEXT NOT NULL,
+         filter_type TEXT NOT NULL,
+         options TEXT NOT NULL,
+         sort TEXT NOT NULL,
+         created_at INTEGER NOT NULL
+     );
+     CREATE TABLE aliases (alias TEXT PRIMARY KEY, target TEXT NOT NULL);
+     CREATE TABLE index_roots (
+         root TEXT PRIMARY KEY,
+         entries INTEGER NOT NULL,
+         scanned_at INTEGER NOT NULL,
+         scan_ms INTEGER NOT NULL
+     );",
+    // 2: 打开总次数单独记录，每个路径 (及每个查询前缀下的路径) 只保留最近 32 条 (history::MAX_OPENS) 打开时间
+    "CREATE TABLE open_counts (path TEXT PRIMARY KEY, total INTEGER NOT NULL);
+     INSERT INTO open_counts (path, total) SELECT path, count(*) FROM opens GROUP BY path;
+     DELETE FROM opens WHERE rowid IN (
+         SELECT rowid FROM (SELECT rowid, row_number() OVER (PARTITION BY path ORDER BY opened_at DESC, rowid DESC) AS n FROM opens)
+         WHERE n > 32
+     );
+     DELETE FROM query_choices WHERE rowid IN (
+         SELECT rowid FROM (SELECT rowid, row_number() OVER (PARTITION BY prefix, path ORDER BY opened_at DESC, rowid DESC) AS n FROM query_choices)
+         WHERE n > 32
+     );
+     CREATE INDEX query_choices_prefix ON query_choices (prefix);",
+];
+
+fn db_err(e: rusqlite::Error) -> String {
+    format!("数据库错误: {}", e)
+}
+
+// SQLCipher 用错误的密钥 (或读到明文库) 时报 SQLITE_NOTADB
+fn is_not_a_database(e: &rusqlite::Error) -> bool {
+    matches!(e, rusqlite::Error::SqliteFailure(f, _) if f.code == rusqlite::ErrorCode::NotADatabase)
+}
+
+// 明文 SQLite 文件以固定的文件头开始，加密后的文件头是随机的
+fn is_plaintext(path: &Path) -> bool {
+    let mut header = [0u8; 16];
+    std::fs::File::open(path).and_then(|mut f| std::io::Read::read_exact(&mut f, &mut header)).is_ok() && &header == b"SQLite format 3\0"
+}
+
+/// 保存的搜索
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SavedSearch {
+    pub name: String,
+    pub keyword: String,
+    pub filter_type: String,
+    #[serde(default)]
+    pub options: SearchOptions,
+    #[serde(default)]
+    pub sort: SortOrder,
+    /// 保存时间 (Unix 秒)，由后端填写
+    #[serde(default)]
+    pub created_at: u64,
+}
+
+/// 一个扫描根目录最近一次扫描的情况
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexRoot {
+    pub root: String,
+    pub entries: u64,
+    /// 扫描完成时间 (Unix 秒)
+    pub scanned_at: u64,
+    pub scan_ms: u64,
+}
+
+pub struct Store {
+    conn: Mutex<Connection>,
+    // 本次启动时数据库是新建的，需要导入旧数据
+    created: bool,
+}
+
+impl Store {
+    /// 打开数据库，`key` 为 SQLCipher 密钥 (见 `Cipher::sqlcipher_key`)
+    pub fn open(path: &Path, key: &str) -> Result<Self, String> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| format!("无法创建数据目录: {}", e))?;
+        }
+        let conn = match Self::connect(path, key) {
+            Ok(conn) => conn,
+            // 只有「不是数据库」(明文库或密钥不对) 才尝试加密或移开；忙、锁定、权限与 I/O 错误原样返回，
+            // 以免另一个正在运行的实例占用时把正常的数据库当作无法解开而改名
+            Err(e) if !is_not_a_database(&e) => return Err(db_err(e)),
+            // 明文库加密失败时原样报错，不移开
+            Err(_) if is_plaintext(path) => {
+                Self::encrypt_plaintext(path, key)?;
+                println!("已加密旧版本的明文数据库");
+                Self::connect(path, key).map_err(db_err)?
+            }
+            Err(e) => {
+                let aside = Self::set_aside(path)?;
+                eprintln!("数据库无法用当前密钥打开 ({})，已改名为 {} 保留，恢复密钥后改回原名即可", e, aside.display());
+                Self::connect(path, key).map_err(db_err)?
+            }
+        };
+        // WAL 模式下后台写入不阻塞读取
+        conn.pragma_update(None, "journal_mode", "WAL").map_err(db_err)?;
+        Self::init(conn)
+    }
+
+    // 打开并确认密钥正确 (SQLCipher 在第一次读取时才校验密钥)
+    fn connect(path: &Path, key: &str) -> rusqlite::Result<Connection> {
+        let conn = Connection::open(path)?;
+        conn.pragma_update(None, "key", key)?;
+        conn.query_row("SELECT count(*) FROM sqlite_master", [], |_| Ok(()))?;
+        Ok(conn)
+    }
+
+    // 把旧版本的明文数据库导出为加密副本后替换原文件
+    fn encrypt_plaintext(path: &Path, key: &str) -> Result<(), String> {
+        let encrypted = path.with_extension("db.encrypting");
+        let _ = std::fs::remove_file(&encrypted);
+        let plain = Connection::open(path).map_err(db_err)?;
+        let version = plain.pragma_query_value(None, "user_version", |r| r.get::<_, i64>(0)).map_err(db_err)?;
+        plain
+            .execute("ATTACH DATABASE ?1 AS encrypted KEY ?2", params![encrypted.to_string_lossy(), key])
+            .map_err(db_err)?;
+        plain.query_row("SELECT sqlcipher_export('encrypted')", [], |_| Ok(())).map_err(db_err)?;
+        plain.pragma_update(Some("encrypted"), "user_version", version).map_err(db_err)?;
+        plain.execute("DETACH DATABASE encrypted", []).map_err(db_err)?;
+        plain.close().map_err(|(_, e)| db_err(e))?;
+        // 明文的 WAL 文件不能留给加密后的数据库
+        for suffix in ["-wal", "-shm"] {
+            let _ = std::fs::remove_file(with_suffix(path, suffix));
+        }
+        std::fs::rename(&encrypted, path).map_err(|e| format!("无法替换数据库文件: {}", e))
+    }
+
+    // 把无法解开的数据库连同 WAL 文件改名保留，返回新文件名
+    fn set_aside(path: &Path) -> Result<std::path::PathBuf, String> {
+        let aside = with_suffix(path, &format!(".unreadable-{}", crate::history::unix_now()));
+        std::fs::rename(path, &aside).map_err(|e| format!("无法移开数据库文件: {}", e))?;
+        for suffix in ["-wal", "-shm"] {
+            let _ = std::fs::rename(with_suffix(path, suffix), with_suffix(&aside, suffix));
+        }
+        Ok(aside)
+    }
+
+    /// 内存数据库，数据库文件无法打开时退而使用 (本次运行的改动不会保存)
+    pub fn in_memory() -> Self {
+        let conn = Connection::open_in_memory().expect("无法创建内存数据库");
+        Self::init(conn).expect("无法初始化内存数据库")
+    }
+
+    fn init(mut conn: Connection) -> Result<Self, String> {
+        let version = conn.pragma_query_value(None, "user_version", |r| r.get::<_, i64>(0)).map_err(db_err)? as usize;
+        if version > MIGRATIONS.len() {
+            return Err(format!("数据库版本 {} 高于当前程序支持的版本 {}", version, MIGRATIONS.len()));
+        }
+        for (i, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+            let tx = conn.transaction().map_err(db_err)?;
+            tx.execute_batch(migration).map_err(db_err)?;
+            tx.pragma_update(None, "user_version", (i + 1) as i64).map_err(db_err)?;
+            tx.commit().map_err(db_err)?;
+        }
+        Ok(Self { conn: Mutex::new(conn), created: version == 0 })
+    }
+
+    /// 数据库是否在本次启动时新建
+    pub fn created(&self) -> bool {
+        self.created
+    }
+
+    /// 导入早期版本的 click_history.json，成功后删除明文文件；返回导入的打开次数
+    pub fn import_click_history(&self, json: &Path) -> Result<usize, String> {
+        let Ok(content) = std::fs::read_to_string(json) else { return Ok(0) };
+        // 最早的格式只有点击次数，以文件最后修改时间作为这些点击的发生时间
+        let legacy_time = std::fs::metadata(json)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map_or_else(crate::history::unix_now, |d| d.as_secs());
+        let history = ClickHistory::parse(&content, legacy_time)?;
+        let count = self.rewrite_history(&history)?;
+        let _ = std::fs::remove_file(json);
+        Ok(count)
+    }
+
+    /// 读出全部点击历史；超出上限的记录在内存中一次淘汰，并同步从数据库删除
+    pub fn load_history(&self) -> Result<ClickHistory, String> {
+        let (history, evicted) = {
+            let conn = self.conn.lock().unwrap();
+            let mut stmt = conn.prepare("SELECT path, opened_at FROM opens ORDER BY opened_at").map_err(db_err)?;
+            let opens = stmt
+                .query_map([], |r| Ok((r.get::<_, String>(0)?, r.get::<_, i64>(1)? as u64)))
+                .map_err(db_err)?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(db_err)?;
+            let mut stmt = conn.prepare("SELECT path, total FROM open_counts").map_err(db_err)?;
+            let counts = stmt
+                .query_map([], |r| Ok((r.get::<_, String>(0)?, r.get::<_, i64>(1)? as u32)))
+                .map_err(db_err)?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(db_err)?;
+            let mut stmt = conn.prepare("SELECT prefix, path, opened_at FROM query_choices ORDER BY opened_at").map_err(db_err)?;
+            let choices = stmt
+                .query_map([], |r| Ok((r.get::<_, String>(0)?, r.get::<_, String>(1)?, r.get::<_, i64>(2)? as u64)))
+                .map_err(db_err)?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(db_err)?;
+            ClickHistory::from_records(opens, counts, choices, crate::history::unix_now())
+        };
+        if let Some(evicted) = evicted {
+            self.apply_history(&[evicted])?;
+        }
+        Ok(history)
+    }
+
+    // 用内存中的历史整体替换数据库中的记录，只在数据库新建 (user_version 为 0) 后导入旧数据时进行；返回打开次数
+    fn rewrite_history(&self, history: &ClickHistory) -> Result<usize, String> {
+        let (opens, counts, choices) = history.export();
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction().map_err(db_err)?;
+        tx.execute_batch("DELETE FROM opens; DELETE FROM open_counts; DELETE FROM query_choices;").map_err(db_err)?;
+        {
+            let mut insert = tx.prepare("INSERT INTO opens (path, opened_at) VALUES (?1, ?2)").map_err(db_err)?;
+            for (path, at) in &opens {
+                insert.execute(params![path, *at as i64]).map_err(db_err)?;
+            }
+            let mut insert = tx.prepare("INSERT INTO open_counts (path, total) VALUES (?1, ?2)").map_err(db_err)?;
+            for (path, total) in &counts {
+                insert.execute(params![path, *total as i64]).map_err(db_err)?;
+            }
+            let mut insert = tx.prepare("INSERT INTO query_choices (prefix, path, opened_at) VALUES (?1, ?2, ?3)").map_err(db_err)?;
+            for (prefix, path, at) in &choices {
+                insert.execute(params![prefix, path, *at as i64]).map_err(db_err)?;
+            }
+        }
+        tx.commit().map_err(db_err)?;
+        Ok(counts.iter().map(|(_, total)| *total as usize).sum())
+    }
+
+    // 每个路径及其在各查询前缀下只保留最近 MAX_OPENS 条打开时间，与内存中的历史一致
+    fn trim_opens(tx: &rusqlite::Transaction, path: &str) -> Result<(), String> {
+        tx.execute(
+            "DELETE FROM opens WHERE rowid IN (
+                 SELECT rowid FROM (SELECT rowid, row_number() OVER (ORDER BY opened_at DESC, rowid DESC) AS n FROM opens WHERE path = ?1)
+                 WHERE n > ?2
+             )",
+            params![path, MAX_OPENS as i64],
+        )
+        .map_err(db_err)?;
+        tx.execute(
+            "DELETE FROM query_choices WHERE rowid IN (
+                 SELECT rowid FROM (SELECT rowid, row_number() OVER (PARTITION BY prefix ORDER BY opened_at DESC, rowid DESC) AS n FROM query_choices WHERE path = ?1)
+                 WHERE n > ?2
+             )",
+            params![path, MAX_OPENS as i64],
+        )
+        .map_err(db_err)?;
+        Ok(())
+    }
+
+    /// 在一个事务中写入一批历史改动
+    pub fn apply_history(&self, ops: &[HistoryOp]) -> Result<(), String> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction().map_err(db_err)?;
+        for op in ops {
+            match op {
+                HistoryOp::Open { path, prefixes, at } => {
+                    tx.execute("INSERT INTO opens (path, opened_at) VALUES (?1, ?2)", params![path, *at as i64]).map_err(db_err)?;
+                    for prefix in prefixes {
+                        tx.execute(
+                            "INSERT INTO query_choices (prefix, path, opened_at) VALUES (?1, ?2, ?3)",
+                            params![prefix, path, *at as i64],
+                        )
+                        .map_err(db_err)?;
+                    }
+                    tx.execute(
+                        "INSERT INTO open_counts (path, total) VALUES (?1, 1) ON CONFLICT (path) DO UPDATE SET total = total + 1",
+                        params![path],
+                    )
+                    .map_err(db_err)?;
+                    Self::trim_opens(&tx, path)?;
+                }
+                HistoryOp::Rename { from, to } => {
+                    tx.execute("UPDATE opens SET path = ?2 WHERE path = ?1", params![from, to]).map_err(db_err)?;
+                    tx.execute("UPDATE query_choices SET path = ?2 WHERE path = ?1", params![from, to]).map_err(db_err)?;
+                    tx.execute(
+                        "INSERT INTO open_counts (path, total) SELECT ?2, total FROM open_counts WHERE path = ?1
+                         ON CONFLICT (path) DO UPDATE SET total = total + excluded.total",
+                        params![from, to],
+                    )
+                    .map_err(db_err)?;
+                    tx.execute("DELETE FROM open_counts WHERE path = ?1", params![from]).map_err(db_err)?;
+                    Self::trim_opens(&tx, to)?;
+                }
+                HistoryOp::Forget { path } => {
+                    tx.execute("DELETE FROM opens WHERE path = ?1", params![path]).map_err(db_err)?;
+                    tx.execute("DELETE FROM open_counts WHERE path = ?1", params![path]).map_err(db_err)?;
+                    tx.execute("DELETE FROM query_choices WHERE path = ?1", params![path]).map_err(db_err)?;
+                }
+                HistoryOp::Evict { paths, prefixes } => {
+                    for path in paths {
+                        tx.execute("DELETE FROM opens WHERE path = ?1", params![path]).map_err(db_err)?;
+                        tx.execute("DELETE FROM open_counts WHERE path = ?1", params![path]).map_err(db_err)?;
+                    }
+                    for prefix in prefixes {
+                        tx.execute("DELETE FROM query_choices WHERE prefix = ?1", params![prefix]).map_err(db_err)?;
+                    }
+                }
+                HistoryOp::Clear => tx.execute_batch("DELETE FROM opens; DELETE FROM open_counts; DELETE FROM query_choices;").map_err(db_err)?,
+            }
+        }
+        tx.commit().map_err(db_err)
+    }
+
+    pub fn settings(&self) -> Result<HashMap<String, serde_json::Value>, String> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT key, value FROM settings").map_err(db_err)?;
+        let rows = stmt.query_map([], |r| Ok((r.get::<_, String>(0)?, r.get::<_, String>(1)?))).map_err(db_err)?;
+        let mut settings = HashMap::new();
+        for row in rows {
+            let (key, value) = row.map_err(db_err)?;
+            // 单个值损坏时跳过，不影响其它设置
+            if let Ok(value) = serde_json::from_str(&value) {
+                settings.insert(key, value);
+            }
+        }
+        Ok(settings)
+    }
+
+    /// 保存一项设置，值为 null 时删除
+    pub fn set_setting(&self, key: &str, value: &serde_json::Value) -> Result<(), String> {
+        let conn = self.conn.lock().unwrap();
+        if value.is_null() {
+            conn.execute("DELETE FROM settings WHERE key = ?1", params![key]).map_err(db_err)?;
+        } else {
+            conn.execute(
+                "INSERT INTO settings (key, value) VALUES (?1, ?2) ON CONFLICT (key) DO UPDATE SET value = excluded.value",
+                params![key, value.to_string()],
+            )
+            .map_err(db_err)?;
+        }
+        Ok(())
+    }
+
+    pub fn saved_searches(&self) -> Result<Vec<SavedSearch>, String> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn
+            .prepare("SELECT name, keyword, filter_type, options, sort, created_at FROM saved_searches ORDER BY created_at, name")
+            .map_err(db_err)?;
+        let rows = stmt
+            .query_map([], |r| {
+                let options: String = r.get(3)?;
+                let sort: String = r.get(4)?;
+                Ok(SavedSearch {
+                    name: r.get(0)?,
+                    keyword: r.get(1)?,
+                    filter_type: r.get(2)?,
+                    options: serde_json::from_str(&options).unwrap_or_default(),
+                    sort: serde_json::from_str(&sort).unwrap_or_default(),
+                    created_at: r.get::<_, i64>(5)? as u64,
+                })
+            })
+            .map_err(db_err)?;
+        rows.collect::<Result<_, _>>().map_err(db_err)
+    }
+
+    /// 保存搜索，同名时覆盖
+    pub fn save_search(&self, search: &SavedSearch) -> Result<(), String> {
+        let options = serde_json::to_string(&search.options).map_err(|e| e.to_string())?;
+        let sort = serde_json::to_string(&search.sort).map_err(|e| e.to_string())?;
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR REPLACE INTO saved_searches (name, keyword, filter_type, options, sort, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![search.name, search.keyword, search.filter_type, options, sort, search.created_at as i64],
+        )
+        .map_err(db_err)?;
+        Ok(())
+    }
+
+    /// 删除保存的搜索，返回是否存在
+    pub fn delete_search(&self, name: &str) -> Result<bool, String> {
+        let conn = self.conn.lock().unwrap();
+        let n = conn.execute("DELETE FROM saved_searches WHERE name = ?1", params![name]).map_err(db_err)?;
+        Ok(n > 0)
+    }
+
+    /// 取出并删除早期版本保存在数据库中的自定义别名 (现在保存在 aliases.json 中)
+    pub fn take_aliases(&self) -> Result<Vec<(String, String)>, String> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction().map_err(db_err)?;
+        let aliases = {
+            let mut stmt = tx.prepare("SELECT alias, target FROM aliases ORDER BY alias").map_err(db_err)?;
+            let rows = stmt.query_map([], |r| Ok((r.get(0)?, r.get(1)?))).map_err(db_err)?;
+            rows.collect::<Result<Vec<_>, _>>().map_err(db_err)?
+        };
+        tx.execute("DELETE FROM aliases", []).map_err(db_err)?;
+        tx.commit().map_err(db_err)?;
+        Ok(aliases)
+    }
+
+    pub fn index_roots(&self) -> Result<Vec<IndexRoot>, String> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT root, entries, scanned_at, scan_ms FROM index_roots ORDER BY root").map_err(db_err)?;
+        let rows = stmt
+            .query_map([], |r| {
+                Ok(IndexRoot {
+                    root: r.get(0)?,
+                    entries: r.get::<_, i64>(1)? as u64,
+                    scanned_at: r.get::<_, i64>(2)? as u64,
+                    scan_ms: r.get::<_, i64>(3)? as u64,
+                })
+            })
+            .map_err(db_err)?;
+        rows.collect::<Result<_, _>>().map_err(db_err)
+    }
+
+    /// 记录一轮扫描各根目录的情况；本轮没有扫描的根目录 (如已拔出的外接盘) 一并移除
+    pub fn record_index_roots(&self, roots: &[IndexRoot]) -> Result<(), String> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction().map_err(db_err)?;
+        tx.execute("DELETE FROM index_roots", []).map_err(db_err)?;
+        for root in roots {
+            tx.execute(
+                "INSERT INTO index_roots (root, entries, scanned_at, scan_ms) VALUES (?1, ?2, ?3, ?4)",
+                params![root.root, root.entries as i64, root.scanned_at as i64, root.scan_ms as i64],
+            )
+            .map_err(db_err)?;
+        }
+        tx.commit().map_err(db_err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::MAX_PATHS;
+
+    const DAY: u64 = 86400;
+
+    #[test]
+    fn history_ops_round_trip() {
+        let store = Store::in_memory();
+        assert!(store.created());
+        store
+            .apply_history(&[
+                HistoryOp::open("/docs/a/plan.key", Some("plan"), 10 * DAY),
+                HistoryOp::open("/docs/notes.txt", None, 11 * DAY),
+                HistoryOp::open("/docs/old.txt", None, 11 * DAY),
+                HistoryOp::Rename { from: "/docs/a/plan.key".to_string(), to: "/docs/b/plan.key".to_string() },
+                HistoryOp::Forget { path: "/docs/old.txt".to_string() },
+            ])
+            .unwrap();
+        let history = store.load_history().unwrap();
+        assert_eq!(history.paths(), vec!["/docs/b/plan.key", "/docs/notes.txt"]);
+        assert_eq!(history.learned("pl", "/docs/b/plan.key", 0.0, 10 * DAY), 1.0);
+
+        store.apply_history(&[HistoryOp::Clear]).unwrap();
+        assert_eq!(store.load_history().unwrap().len(), 0);
+    }
+
+    fn rows(store: &Store, sql: &str) -> i64 {
+        store.conn.lock().unwrap().query_row(sql, [], |r| r.get(0)).unwrap()
+    }
+
+    #[test]
+    fn history_rows_stay_bounded() {
+        let store = Store::in_memory();
+        let mut history = ClickHistory::default();
+        let mut ops = Vec::new();
+        // 与 AppCache::change_history 一样，淘汰跟在引起它的改动之后写入
+        let mut change = |op: HistoryOp| {
+            let evicted = history.apply(&op);
+            ops.push(op);
+            ops.extend(evicted);
+        };
+        for i in 0..100 {
+            change(HistoryOp::open("/docs/plan.key", Some("plan"), 100 * DAY + i));
+        }
+        for i in 0..MAX_PATHS + 10 {
+            change(HistoryOp::open(&format!("/old/{}.txt", i), None, 10 * DAY));
+        }
+        store.apply_history(&ops).unwrap();
+
+        assert_eq!(rows(&store, "SELECT count(*) FROM opens WHERE path = '/docs/plan.key'"), MAX_OPENS as i64);
+        assert_eq!(rows(&store, "SELECT count(*) FROM open_counts"), MAX_PATHS as i64);
+        assert_eq!(rows(&store, "SELECT count(*) FROM opens"), (MAX_OPENS + MAX_PATHS - 1) as i64);
+        // pl、pla、plan 三个前缀各保留 MAX_OPENS 条
+        assert_eq!(rows(&store, "SELECT count(*) FROM query_choices"), 3 * MAX_OPENS as i64);
+
+        let loaded = store.load_history().unwrap();
+        assert_eq!(loaded.len(), MAX_PATHS);
+        assert_eq!(loaded.total("/docs/plan.key"), 100);
+        assert_eq!(loaded.paths(), history.paths());
+    }
+
+    #[test]
+    fn migration_trims_legacy_history_rows() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(MIGRATIONS[0]).unwrap();
+        conn.pragma_update(None, "user_version", 1).unwrap();
+        for i in 0..40 {
+            conn.execute("INSERT INTO opens (path, opened_at) VALUES ('/a.txt', ?1)", params![i]).unwrap();
+            conn.execute("INSERT INTO query_choices (prefix, path, opened_at) VALUES ('ab', '/a.txt', ?1)", params![i]).unwrap();
+        }
+        let store = Store::init(conn).unwrap();
+        assert!(!store.created());
+        assert_eq!(rows(&store, "SELECT count(*) FROM opens"), MAX_OPENS as i64);
+        assert_eq!(rows(&store, "SELECT count(*) FROM query_choices"), MAX_OPENS as i64);
+        // 保留的是最近的打开，总次数不变
+        assert_eq!(rows(&store, "SELECT min(opened_at) FROM opens"), (40 - MAX_OPENS) as i64);
+        assert_eq!(store.load_history().unwrap().total("/a.txt"), 40);
+        // 迁移中写死的条数与 MAX_OPENS 一致
+        assert!(MIGRATIONS[1].contains(&format!("n > {}", MAX_OPENS)));
+    }
+
+    const KEY: &str = "x'00112233445566778899aabbccddeeff00112233445566778899aabbccddeeff'";
+
+    #[test]
+    fn imports_legacy_click_history() {
+        let dir = std::env::temp_dir().join(format!("xtap-store-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let json = dir.join("click_history.json");
+        std::fs::write(&json, r#"{"/Applications/WeChat.app": 3}"#).unwrap();
+
+        let store = Store::open(&dir.join("store.db"), KEY).unwrap();
+        assert!(store.created());
+        assert_eq!(store.import_click_history(&json).unwrap(), 3);
+        assert!(!json.exists());
+        assert_eq!(store.load_history().unwrap().total("/Applications/WeChat.app"), 3);
+        drop(store);
+
+        // 再次打开时已是最新版本，不再导入
+        let store = Store::open(&dir.join("store.db"), KEY).unwrap();
+        assert!(!store.created());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn encrypts_plaintext_and_sets_aside_unreadable() {
+        let dir = std::env::temp_dir().join(format!("xtap-store-crypt-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("store.db");
+
+        // 旧版本的明文数据库
+        let plain = Store::init(Connection::open(&path).unwrap()).unwrap();
+        plain.set_setting("editor", &serde_json::json!("webstorm")).unwrap();
+        drop(plain);
+
+        let store = Store::open(&path, KEY).unwrap();
+        assert!(!store.created());
+        assert_eq!(store.settings().unwrap()["editor"], "webstorm");
+        drop(store);
+        let raw = std::fs::read(&path).unwrap();
+        assert!(!raw.starts_with(b"SQLite format 3"));
+        assert!(!raw.windows(8).any(|w| w == b"webstorm"));
+
+        // 换了密钥：原文件改名保留，另建新库
+        let other = "x'ffeeddccbbaa99887766554433221100ffeeddccbbaa99887766554433221100'";
+        let store = Store::open(&path, other).unwrap();
+        assert!(store.created());
+        assert!(store.settings().unwrap().is_empty());
+        let aside = std::fs::read_dir(&dir).unwrap().flatten().find(|e| e.file_name().to_string_lossy().contains(".unreadable-"));
+        assert!(aside.is_some());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn open_errors_leave_database_in_place() {
+        let dir = std::env::temp_dir().join(format!("xtap-store-open-err-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        // 路径是目录：打不开但不是 NOTADB，应原样报错，不改名
+        let path = dir.join("store.db");
+        std::fs::create_dir_all(&path).unwrap();
+        assert!(Store::open(&path, KEY).is_err());
+        assert!(path.is_dir());
+        let aside = std::fs::read_dir(&dir).unwrap().flatten().any(|e| e.file_name().to_string_lossy().contains(".unreadable-"));
+        assert!(!aside);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn settings_searches_aliases_and_roots() {
+        let store = Store::in_memory();
+        store.set_setting("sortOrder", &serde_json::json!({ "key": "modified" })).unwrap();
+        assert_eq!(store.settings().unwrap()["sortOrder"]["key"], "modified");
+        store.set_setting("sortOrder", &serde_json::Value::Null).unwrap();
+        assert!(store.settings().unwrap().is_empty());
+
+        let search = SavedSearch {
+            name: "报告".to_string(),
+            keyword: "report 2024".to_string(),
+            filter_type: "doc".to_string(),
+            options: SearchOptions { fuzzy: false, ..Default::default() },
+            sort: SortOrder::default(),
+            created_at: 5,
+        };
+        store.save_search(&search).unwrap();
+        assert_eq!(store.saved_searches().unwrap(), vec![search]);
+        assert!(store.delete_search("报告").unwrap());
+        assert!(!store.delete_search("报告").unwrap());
+
+        store.conn.lock().unwrap().execute("INSERT INTO aliases (alias, target) VALUES ('ws', 'webstorm')", []).unwrap();
+        assert_eq!(store.take_aliases().unwrap(), vec![("ws".to_string(), "webstorm".to_string())]);
+        assert!(store.take_aliases().unwrap().is_empty());
+
+        let root = IndexRoot { root: "/Applications".to_string(), entries: 120, scanned_at: 7, scan_ms: 30 };
+        store.record_index_roots(std::slice::from_ref(&root)).unwrap();
+        store.record_index_roots(std::slice::from_ref(&root)).unwrap();
+        assert_eq!(store.index_roots().unwrap(), vec![root]);
+    }
+}
@@ -0,0 +1,270 @@
+// 三元组倒排索引：千万级索引上先为子串查询圈定候选，再交给 Query 逐条复核
+//
+// 文件名与所在目录分开建索引：同一目录下的文件共享目录路径，每个目录只记一次，
+// 内存约为「文件名字节数 + 不重复目录字节数」的量级，而不是每条完整路径都展开。
+// 三元组取折叠后比较键的 UTF-8 字节，一个汉字正好是一个三元组。
+// 条目只追加不删除 (删除由 FileIndex 打标记)，倒排表天然保持升序，可增量维护。
+
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+
+use crate::query::{Query, SearchOptions};
+
+type Trigram = [u8; 3];
+
+/// 持久化文件的版本号，格式变化时递增使旧文件失效
+const FORMAT_VERSION: u32 = 1;
+const MAGIC: &[u8; 4] = b"XTRI";
+
+#[derive(Default)]
+pub struct TrigramIndex {
+    // 三元组 -> 文件名包含它的条目
+    names: HashMap<Trigram, Vec<u32>>,
+    // 三元组 -> 路径包含它的目录
+    dirs: HashMap<Trigram, Vec<u32>>,
+    // 目录 -> 其下的条目
+    dir_entries: Vec<Vec<u32>>,
+    dir_ids: HashMap<String, u32>,
+}
+
+fn trigrams(s: &str) -> Vec<Trigram> {
+    let mut grams: Vec<Trigram> = s.as_bytes().windows(3).map(|w| [w[0], w[1], w[2]]).collect();
+    grams.sort_unstable();
+    grams.dedup();
+    grams
+}
+
+fn intersect(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let (mut i, mut j) = (0, 0);
+    let mut out = Vec::new();
+    while i < a.len() && j < b.len() {
+        match a[i].cmp(&b[j]) {
+            std::cmp::Ordering::Less => i += 1,
+            std::cmp::Ordering::Greater => j += 1,
+            std::cmp::Ordering::Equal => {
+                out.push(a[i]);
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    out
+}
+
+fn union(mut a: Vec<u32>, b: &[u32]) -> Vec<u32> {
+    a.extend_from_slice(b);
+    a.sort_unstable();
+    a.dedup();
+    a
+}
+
+// 所有三元组倒排表的交集，从最短的表开始
+fn lookup_all(postings: &HashMap<Trigram, Vec<u32>>, grams: &[Trigram]) -> Vec<u32> {
+    let mut lists = Vec::with_capacity(grams.len());
+    for g in grams {
+        match postings.get(g) {
+            Some(list) => lists.push(list.as_slice()),
+            None => return Vec::new(),
+        }
+    }
+    lists.sort_by_key(|l| l.len());
+    let Some((first, rest)) = lists.split_first() else { return Vec::new() };
+    let mut result = first.to_vec();
+    for list in rest {
+        if result.is_empty() {
+            break;
+        }
+        result = intersect(&result, list);
+    }
+    result
+}
+
+impl TrigramIndex {
+    /// 追加一个条目。`dir` 为折叠后的目录部分 (含末尾的 '/')，`name` 为折叠后的文件名；
+    /// `id` 必须大于此前所有条目。
+    pub fn insert(&mut self, id: u32, dir: &str, name: &str) {
+        let dir_id = match self.dir_ids.get(dir) {
+            Some(&d) => d,
+            None => {
+                let d = self.dir_entries.len() as u32;
+                self.dir_entries.push(Vec::new());
+                self.dir_ids.insert(dir.to_string(), d);
+                for g in trigrams(dir) {
+                    self.dirs.entry(g).or_default().push(d);
+                }
+                d
+            }
+        };
+        self.dir_entries[dir_id as usize].push(id);
+        for g in trigrams(name) {
+            self.names.entry(g).or_default().push(id);
+        }
+    }
+
+    /// 文件名或路径可能包含 `term` (已按比较键规则折叠) 的条目，升序；词太短无法圈定时返回 None
+    pub fn lookup(&self, term: &str, match_path: bool) -> Option<Vec<u32>> {
+        // 含 '/' 的词可能横跨目录与文件名，三元组无法覆盖
+        if term.len() < 3 || term.contains('/') {
+            return None;
+        }
+        let grams = trigrams(term);
+        let mut hits = lookup_all(&self.names, &grams);
+        if match_path {
+            let mut in_dirs = Vec::new();
+            for d in lookup_all(&self.dirs, &grams) {
+                in_dirs.extend_from_slice(&self.dir_entries[d as usize]);
+            }
+            hits = union(hits, &in_dirs);
+        }
+        Some(hits)
+    }
+
+    /// 可能严格命中查询的条目 (每个词都被包含，或命中该词的别名目标)；
+    /// 任意一个词无法圈定时返回 None，调用方应退回全量扫描
+    pub fn candidates(&self, query: &Query) -> Option<Vec<u32>> {
+        // 比较键按默认选项折叠，查询词也要折叠到同一形式 (区分大小写、关闭简繁时得到的是超集)
+        let key_options = SearchOptions::default();
+        let mut result: Option<Vec<u32>> = None;
+        for (term, targets) in query.terms.iter().zip(&query.term_aliases) {
+            let mut hits = self.lookup(&key_options.fold_text(term, true), query.options.match_path)?;
+            // 别名目标只在文件名中查找
+            for target in targets {
+                hits = union(hits, &self.lookup(&key_options.fold_text(target, true), false)?);
+            }
+            result = Some(match result {
+                Some(prev) => intersect(&prev, &hits),
+                None => hits,
+            });
+        }
+        result
+    }
+
+    pub fn save(&self, out: &mut impl Write, entries: u64, checksum: u64) -> io::Result<()> {
+        out.write_all(MAGIC)?;
+        write_u32(out, FORMAT_VERSION)?;
+        out.write_all(&entries.to_le_bytes())?;
+        out.write_all(&checksum.to_le_bytes())?;
+
+        let mut dirs: Vec<(&String, &u32)> = self.dir_ids.iter().collect();
+        dirs.sort_by_key(|(_, &d)| d);
+        write_u32(out, dirs.len() as u32)?;
+        for (dir, &d) in dirs {
+            write_u32(out, dir.len() as u32)?;
+            out.write_all(dir.as_bytes())?;
+            write_list(out, &self.dir_entries[d as usize])?;
+        }
+        for postings in [&self.names, &self.dirs] {
+            write_u32(out, postings.len() as u32)?;
+            for (gram, list) in postings {
+                out.write_all(gram)?;
+                write_list(out, list)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 读取持久化的三元组索引；与当前索引缓存不匹配 (条目数或校验和不同) 时返回 None
+    pub fn load(input: &mut impl Read, entries: u64, checksum: u64) -> io::Result<Option<Self>> {
+        let mut magic = [0u8; 4];
+        input.read_exact(&mut magic)?;
+        if &magic != MAGIC || read_u32(input)? != FORMAT_VERSION || read_u64(input)? != entries || read_u64(input)? != checksum {
+            return Ok(None);
+        }
+
+        let mut index = Self::default();
+        for d in 0..read_u32(input)? {
+            let mut dir = vec![0u8; read_u32(input)? as usize];
+            input.read_exact(&mut dir)?;
+            let dir = String::from_utf8(dir).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            index.dir_ids.insert(dir, d);
+            index.dir_entries.push(read_list(input)?);
+        }
+        for postings in [&mut index.names, &mut index.dirs] {
+            for _ in 0..read_u32(input)? {
+                let mut gram = [0u8; 3];
+                input.read_exact(&mut gram)?;
+                postings.insert(gram, read_list(input)?);
+            }
+        }
+        Ok(Some(index))
+    }
+}
+
+fn write_u32(out: &mut impl Write, v: u32) -> io::Result<()> {
+    out.write_all(&v.to_le_bytes())
+}
+
+fn write_list(out: &mut impl Write, list: &[u32]) -> io::Result<()> {
+    write_u32(out, list.len() as u32)?;
+    for &v in list {
+        write_u32(out, v)?;
+    }
+    Ok(())
+}
+
+fn read_u32(input: &mut impl Read) -> io::Result<u32> {
+    let mut buf = [0u8; 4];
+    input.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64(input: &mut impl Read) -> io::Result<u64> {
+    let mut buf = [0u8; 8];
+    input.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+fn read_list(input: &mut impl Read) -> io::Result<Vec<u32>> {
+    let len = read_u32(input)? as usize;
+    let mut list = Vec::with_capacity(len);
+    for _ in 0..len {
+        list.push(read_u32(input)?);
+    }
+    Ok(list)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> TrigramIndex {
+        let mut index = TrigramIndex::default();
+        index.insert(0, "/users/me/documents/", "report 2024.pdf");
+        index.insert(1, "/users/me/report/", "notes.txt");
+        index.insert(2, "/applications/", "photoshop.app");
+        index
+    }
+
+    #[test]
+    fn lookup_names_and_dirs() {
+        let index = sample();
+        assert_eq!(index.lookup("report", true), Some(vec![0, 1]));
+        assert_eq!(index.lookup("report", false), Some(vec![0]));
+        assert_eq!(index.lookup("shop", true), Some(vec![2]));
+        assert_eq!(index.lookup("zzz", true), Some(vec![]));
+        // 太短或跨越目录的词无法圈定
+        assert_eq!(index.lookup("re", true), None);
+        assert_eq!(index.lookup("me/report", true), None);
+    }
+
+    #[test]
+    fn candidates_expand_each_term_with_its_aliases() {
+        let index = sample();
+        let mapping = std::collections::HashMap::from([("image".to_string(), vec!["photoshop".to_string()])]);
+        let query = Query::new("image app", SearchOptions::default(), &mapping);
+        assert_eq!(index.candidates(&query), Some(vec![2]));
+        let query = Query::new("image pdf", SearchOptions::default(), &mapping);
+        assert_eq!(index.candidates(&query), Some(vec![]));
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let index = sample();
+        let mut buf = Vec::new();
+        index.save(&mut buf, 3, 42).unwrap();
+        assert!(TrigramIndex::load(&mut buf.as_slice(), 3, 7).unwrap().is_none());
+        let loaded = TrigramIndex::load(&mut buf.as_slice(), 3, 42).unwrap().unwrap();
+        assert_eq!(loaded.lookup("report", true), Some(vec![0, 1]));
+        assert_eq!(loaded.lookup("photo", true), Some(vec![2]));
+    }
+}
@@ -28,6 +28,24 @@ let currentFilter = 'all';
 let lastSearchKeyword = '';
 let isComposing = false;
 
+// 分页状态：每页条数、已加载条数与匹配总数；totalComplete 为 false 时只列出了严格命中
+const PAGE_SIZE = 100;
+let loadedCount = 0;
+let totalCount = 0;
+let totalComplete = true;
+let isLoadingMore = false;
+
+// 查询序号：每次新搜索递增，后端据此取消旧查询，前端据此丢弃过期响应
+let searchSeq = 0;
+
+// 排序方式；direction 为 null 时使用后端为该排序键定义的默认方向
+const sortOrder = { key: 'relevance', direction: null };
+const SORT_DIRECTION_LABELS = { null: '默认', asc: '升序 ↑', desc: '降序 ↓' };
+// 排序调试：在控制台执行 localStorage.setItem('explainRanking', '1') 后，悬停结果可查看得分明细
+const explainRanking = localStorage.getItem('explainRanking') === '1';
+// 无痕模式：开启后后端不记录打开历史
+let privateMode = false;
+
 async function performSearch(force = false) {
   const keyword = searchInput.value.trim();
   
@@ -43,15 +61,44 @@ async function performSearch(force = false) {
     resultsContainer.innerHTML = '<div class="loading">V5 引擎正在极速扫描...</div>';
   }
 
+  const seq = ++searchSeq;
   try {
-    const results = await invoke("search_files", { keyword, filterType: currentFilter });
-    renderResults(results);
+    const page = await invoke("search_files", { keyword, filterType: currentFilter, offset: 0, limit: PAGE_SIZE, seq, sort: sortOrder, explain: explainRanking });
+    // 已有更新的查询，丢弃本次结果
+    if (page.cancelled || page.seq !== searchSeq) return;
+    totalCount = page.total;
+    totalComplete = page.complete;
+    loadedCount = 0;
+    renderResults(page.results);
   } catch (error) {
+    if (seq !== searchSeq) return;
     console.error("搜索出错:", error);
     resultsContainer.innerHTML = `<div class="error">搜索失败: ${error}</div>`;
   }
 }
 
+// 滚动到底部时加载下一页
+async function loadMore() {
+  if (isLoadingMore || loadedCount >= totalCount) return;
+  isLoadingMore = true;
+  const keyword = lastSearchKeyword;
+  const seq = searchSeq;
+  try {
+    // 翻页沿用当前查询的序号，不会取消自身
+    const page = await invoke("search_files", { keyword, filterType: currentFilter, offset: loadedCount, limit: PAGE_SIZE, seq, sort: sortOrder, explain: explainRanking });
+    // 加载期间已发起新查询则丢弃
+    if (!page.cancelled && page.seq === searchSeq) {
+      totalCount = page.total;
+      totalComplete = page.complete;
+      renderResults(page.results, true);
+    }
+  } catch (error) {
+    console.error("加载更多失败:", error);
+  } finally {
+    isLoadingMore = false;
+  }
+}
+
 function getFileIcon(result) {
   const path = result.path;
   const name = result.name;
@@ -77,13 +124,35 @@ function getFileIcon(result) {
   return '📄';
 }
 
-function renderResults(results) {
-  resultsContainer.innerHTML = '';
+function escapeHtml(text) {
+  return text.replace(/[&<>"']/g, c => ({ '&': '&amp;', '<': '&lt;', '>': '&gt;', '"': '&quot;', "'": '&#39;' }[c]));
+}
+
+// 按后端给出的命中区间高亮；区间按 Unicode 字符计，用 Array.from 切分
+function highlightText(text, ranges) {
+  if (!ranges || ranges.length === 0) return escapeHtml(text);
+  const chars = Array.from(text);
+  let html = '';
+  let last = 0;
+  for (const [start, end] of ranges) {
+    html += escapeHtml(chars.slice(last, start).join(''));
+    html += `<mark>${escapeHtml(chars.slice(start, end).join(''))}</mark>`;
+    last = end;
+  }
+  return html + escapeHtml(chars.slice(last).join(''));
+}
+
+function renderResults(results, append = false) {
+  if (!append) {
+    resultsContainer.innerHTML = '';
+  }
+  resultsContainer.querySelector('.result-footer')?.remove();
   
-  if (results.length === 0) {
+  if (results.length === 0 && !append) {
     resultsContainer.innerHTML = '<div class="no-results">未找到匹配项，请尝试其他关键字</div>';
     return;
   }
+  loadedCount += results.length;
 
   results.forEach(result => {
     // 增加严格过滤，确保前端不渲染路径或名称为空的坏数据
@@ -94,6 +163,10 @@ function renderResults(results) {
     const item = document.createElement('div');
     item.className = 'result-item';
     
+    if (result.explain) {
+      item.title = [`得分 ${result.explain.total}`, ...result.explain.rules.map(r => `${r.points > 0 ? '+' : ''}${r.points}  ${r.rule}`)].join('\n');
+    }
+
     // 双击打开文件
     item.ondblclick = () => openFile(result.path);
 
@@ -102,8 +175,8 @@ function renderResults(results) {
     item.innerHTML = `
       <div class="result-icon-box">${icon}</div>
       <div class="result-info">
-        <span class="result-name">${result.name}</span>
-        <span class="result-path">${result.path}</span>
+        <span class="result-name">${highlightText(result.name, result.highlight?.nameRanges)}</span>
+        <span class="result-path">${highlightText(result.path, result.highlight?.pathRanges)}</span>
       </div>
       <div class="result-actions">
         <button class="action-btn copy-btn" title="复制路径">复制</button>
@@ -133,12 +206,22 @@ function renderResults(results) {
 
     resultsContainer.appendChild(item);
   });
+
+  const footer = document.createElement('div');
+  footer.className = 'result-footer';
+  footer.textContent = loadedCount < totalCount
+    ? `已显示 ${loadedCount} / 共 ${totalCount} 条，向下滚动加载更多`
+    : `共 ${totalCount} 条`;
+  if (!totalComplete) {
+    footer.textContent += '（仅严格命中，缩写与模糊结果未列出）';
+  }
+  resultsContainer.appendChild(footer);
 }
 
 async function openFile(path) {
   try {
     console.log("正在打开:", path);
-    await invoke("open_file", { path });
+    await invoke("open_file", { path, query: lastSearchKeyword });
   } catch (error) {
     console.error("打开失败:", error);
     alert("无法打开: " + error);
@@ -148,7 +231,7 @@ async function openFile(path) {
 async function openFolder(path) {
   try {
     console.log("正在打开位置:", path);
-    await invoke("open_folder", { path });
+    await invoke("open_folder", { path, query: lastSearchKeyword });
   } catch (error) {
     console.error("打开位置失败:", error);
     alert("无法打开位置: " + error);
@@ -185,6 +268,24 @@ async function copyPath(path, btn) {
   }
 }
 
+// 设置保存在后端数据库中
+async function loadSettings() {
+  try {
+    const settings = await invoke("get_settings");
+    privateMode = settings.privateMode === true;
+    if (settings.sortOrder) {
+      sortOrder.key = settings.sortOrder.key ?? sortOrder.key;
+      sortOrder.direction = settings.sortOrder.direction ?? null;
+    }
+  } catch (error) {
+    console.error("读取设置失败:", error);
+  }
+}
+
+function saveSortOrder() {
+  invoke("set_setting", { key: "sortOrder", value: sortOrder }).catch(error => console.error("保存设置失败:", error));
+}
+
 // 暴露给全局以便 HTML 调用
 window.copyPath = copyPath;
 window.openFolder = openFolder;
@@ -220,6 +321,13 @@ window.addEventListener("DOMContentLoaded", () => {
     }
   });
 
+  // 滚动接近底部时自动翻页
+  resultsContainer.addEventListener("scroll", () => {
+    if (resultsContainer.scrollTop + resultsContainer.clientHeight >= resultsContainer.scrollHeight - 200) {
+      loadMore();
+    }
+  });
+
   // 标签切换监听
   tabs.forEach(tab => {
     tab.addEventListener("click", () => {
@@ -230,6 +338,37 @@ window.addEventListener("DOMContentLoaded", () => {
     });
   });
 
-  // 初始加载显示最近文件
-  performSearch();
+  // 排序切换：方向在 默认 -> 升序 -> 降序 之间循环
+  const sortKey = document.querySelector("#sort-key");
+  const sortDirection = document.querySelector("#sort-direction");
+  sortKey.addEventListener("change", () => {
+    sortOrder.key = sortKey.value;
+    saveSortOrder();
+    performSearch(true);
+  });
+  sortDirection.addEventListener("click", () => {
+    sortOrder.direction = sortOrder.direction === null ? 'asc' : sortOrder.direction === 'asc' ? 'desc' : null;
+    sortDirection.textContent = SORT_DIRECTION_LABELS[sortOrder.direction];
+    saveSortOrder();
+    performSearch(true);
+  });
+
+  const privateButton = document.querySelector("#private-mode");
+  privateButton.addEventListener("click", async () => {
+    try {
+      await invoke("set_private_mode", { enabled: !privateMode });
+      privateMode = !privateMode;
+      privateButton.classList.toggle("active", privateMode);
+    } catch (error) {
+      console.error("切换无痕模式失败:", error);
+    }
+  });
+
+  // 恢复上次的排序方式后，初始加载显示最近文件
+  loadSettings().finally(() => {
+    sortKey.value = sortOrder.key;
+    sortDirection.textContent = SORT_DIRECTION_LABELS[sortOrder.direction];
+    privateButton.classList.toggle("active", privateMode);
+    performSearch();
+  });
 });